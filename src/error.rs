@@ -0,0 +1,31 @@
+use std::time::Duration;
+use reqwest::StatusCode;
+use crate::github::describe_request_error;
+
+/// Why a call to GitHub or GitLab failed.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The token was rejected, or it lacks the rights for the requested operation.
+    #[error("{0}")]
+    Auth(String),
+    /// The request could not be sent or timed out, or its response could not be read.
+    #[error("{}", describe_request_error(.0))]
+    Request(#[from] reqwest::Error),
+    /// The API answered with an unexpected status; `message` says what was being done.
+    #[error("{message}")]
+    Status { status: StatusCode, message: String },
+    /// A GraphQL query came back with these errors instead of data.
+    #[error("GraphQL query failed: {}", .0.join("; "))]
+    GraphQl(Vec<String>),
+    /// A rate limit resets later than the caller is willing to wait.
+    #[error("GitHub rate limit resets in {}s, which would wait longer than --max-wait ({}s) in all", .wait.as_secs(), .max_wait.as_secs())]
+    RateLimited { wait: Duration, max_wait: Duration },
+    /// The proxy could not be reached, or it would not connect to the API.
+    #[error("Could not connect through the proxy {proxy}: {message}")]
+    Proxy { proxy: String, message: String },
+    /// The arguments cannot work, e.g. a token with a newline in it.
+    #[error("{0}")]
+    Invalid(String),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,177 @@
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use globset::GlobMatcher;
+use regex::Regex;
+use serde::Deserialize;
+use crate::github::Repo;
+
+/// Which visibility [`Filters::visibility`] keeps.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    Public,
+    Private,
+    #[default]
+    All,
+}
+
+/// Whether [`Filters::topics`] needs any or all of the topics.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TopicMatch {
+    #[default]
+    Any,
+    All,
+}
+
+impl TopicMatch {
+    /// Whether a repository tagged with `topics` matches the `wanted` ones. GitHub stores
+    /// topics in lowercase, but they are compared case-insensitively to forgive typing.
+    pub fn matches(self, wanted: &[String], topics: &[String]) -> bool {
+        let tagged = |topic: &String| topics.iter().any(|t| t.eq_ignore_ascii_case(topic));
+        match self {
+            TopicMatch::Any => wanted.iter().any(tagged),
+            TopicMatch::All => wanted.iter().all(tagged),
+        }
+    }
+}
+
+/// Filters on the fields of a listed [`Repo`]. A repository must match all of them; the
+/// default matches everything.
+#[derive(Default, Debug)]
+pub struct Filters {
+    /// Matched against the full name (`owner/name`).
+    pub name: Option<Regex>,
+    /// Matched against the name; when any are given, a repository must match one of them.
+    pub include: Vec<GlobMatcher>,
+    /// Matched against the name; a repository must match none of them.
+    pub exclude: Vec<GlobMatcher>,
+    /// Only keep repositories last pushed before this instant.
+    pub pushed_before: Option<DateTime<Utc>>,
+    /// Only keep repositories created at or after the first and before the second instant.
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub empty_only: bool,
+    /// Sizes in KB, both inclusive.
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub forks_only: bool,
+    pub archived_only: bool,
+    pub no_description: bool,
+    pub topics: Vec<String>,
+    pub topic_match: TopicMatch,
+    pub visibility: Visibility,
+}
+
+impl Filters {
+    /// Whether every repository matches.
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.include.is_empty()
+            && self.exclude.is_empty()
+            && self.pushed_before.is_none()
+            && self.created_after.is_none()
+            && self.created_before.is_none()
+            && !self.empty_only
+            && self.min_size.is_none()
+            && self.max_size.is_none()
+            && !self.forks_only
+            && !self.archived_only
+            && !self.no_description
+            && self.topics.is_empty()
+            && self.visibility == Visibility::All
+    }
+
+    /// Whether `repo` passes every filter.
+    pub fn matches(&self, repo: &Repo) -> bool {
+        if let Some(re) = &self.name {
+            if !re.is_match(&repo.full_name) {
+                return false;
+            }
+        }
+        if !self.include.is_empty() && !self.include.iter().any(|glob| glob.is_match(&repo.name)) {
+            return false;
+        }
+        if self.exclude.iter().any(|glob| glob.is_match(&repo.name)) {
+            return false;
+        }
+        if let Some(cutoff) = self.pushed_before {
+            // A repository without a push date is never treated as old enough.
+            if repo.pushed_at.is_none_or(|pushed_at| pushed_at >= cutoff) {
+                return false;
+            }
+        }
+        // Likewise, a repository without a creation date matches no date range.
+        if let Some(start) = self.created_after {
+            if repo.created_at.is_none_or(|created_at| created_at < start) {
+                return false;
+            }
+        }
+        if let Some(end) = self.created_before {
+            if repo.created_at.is_none_or(|created_at| created_at >= end) {
+                return false;
+            }
+        }
+        // GitHub reports a size of 0 for repositories that were never pushed to.
+        if self.empty_only && repo.size != Some(0) {
+            return false;
+        }
+        // A repository without a size, e.g. from GitLab, is in no size range.
+        if self.min_size.is_some_and(|min| repo.size.is_none_or(|size| size < min)) {
+            return false;
+        }
+        if self.max_size.is_some_and(|max| repo.size.is_none_or(|size| size > max)) {
+            return false;
+        }
+        if self.forks_only && repo.fork != Some(true) {
+            return false;
+        }
+        if self.archived_only && repo.archived != Some(true) {
+            return false;
+        }
+        if self.no_description && repo.description.as_deref().is_some_and(|d| !d.trim().is_empty()) {
+            return false;
+        }
+        // Topics come with the listing, so this costs no extra requests. A listing without
+        // topics (older GitHub Enterprise Server) matches nothing.
+        if !self.topics.is_empty() {
+            let tagged = repo.topics.as_deref().is_some_and(|topics| self.topic_match.matches(&self.topics, topics));
+            if !tagged {
+                return false;
+            }
+        }
+        // A repository whose visibility is unknown is excluded from both restricted modes.
+        match self.visibility {
+            Visibility::All => true,
+            Visibility::Public => repo.private == Some(false),
+            Visibility::Private => repo.private == Some(true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topic_match_any_needs_one_topic_and_all_needs_every_topic() {
+        let wanted = vec!["old".to_string(), "Demo".to_string()];
+        let topics = vec!["demo".to_string(), "rust".to_string()];
+        assert!(TopicMatch::Any.matches(&wanted, &topics));
+        assert!(!TopicMatch::All.matches(&wanted, &topics));
+        assert!(TopicMatch::All.matches(&wanted, &["old".to_string(), "demo".to_string()]));
+    }
+
+    #[test]
+    fn size_range_is_inclusive_and_skips_unknown_sizes() {
+        let sized = |size: Option<u64>| -> Repo {
+            serde_json::from_value(serde_json::json!({ "name": "r", "full_name": "me/r", "size": size })).unwrap()
+        };
+        let filters = Filters { min_size: Some(10), max_size: Some(20), ..Filters::default() };
+        assert!(filters.matches(&sized(Some(10))));
+        assert!(filters.matches(&sized(Some(20))));
+        assert!(!filters.matches(&sized(Some(21))));
+        assert!(!filters.matches(&sized(Some(9))));
+        assert!(!filters.matches(&sized(None)));
+    }
+}
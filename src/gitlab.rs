@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use crate::github::Repo;
+
+pub const DEFAULT_BASE_URL: &str = "https://gitlab.com/api/v4";
+
+/// The fields of a GitLab project that have a counterpart in [`Repo`].
+#[derive(Deserialize, Debug)]
+pub struct Project {
+    /// The URL slug, which is what `path_with_namespace` ends in.
+    path: String,
+    path_with_namespace: String,
+    /// `private`, `internal` or `public`.
+    visibility: Option<String>,
+    archived: Option<bool>,
+    /// Only present for forks.
+    forked_from_project: Option<serde_json::Value>,
+    last_activity_at: Option<DateTime<Utc>>,
+    created_at: Option<DateTime<Utc>>,
+    /// Called `tag_list` before GitLab 14.0.
+    #[serde(alias = "tag_list")]
+    topics: Option<Vec<String>>,
+    description: Option<String>,
+    default_branch: Option<String>,
+    star_count: Option<u64>,
+    forks_count: Option<u64>,
+    /// Missing when the project has issues disabled.
+    open_issues_count: Option<u64>,
+}
+
+impl From<Project> for Repo {
+    fn from(project: Project) -> Self {
+        Repo {
+            name: project.path,
+            full_name: project.path_with_namespace,
+            // Internal projects are visible to every signed-in user of the instance, but not
+            // to the public, so they count as private.
+            private: project.visibility.map(|visibility| visibility != "public"),
+            archived: project.archived,
+            fork: Some(project.forked_from_project.is_some()),
+            // GitLab's project templates are configured per group, not per project.
+            is_template: None,
+            // GitLab has no push timestamp in the listing; the last activity is the closest.
+            pushed_at: project.last_activity_at,
+            created_at: project.created_at,
+            // Sizes need `statistics=true`, which requires at least the Reporter role.
+            size: None,
+            topics: project.topics,
+            description: project.description,
+            default_branch: project.default_branch,
+            stargazers_count: project.star_count,
+            forks_count: project.forks_count,
+            open_issues_count: project.open_issues_count,
+        }
+    }
+}
+
+/// Turns `group/subgroup/project` into the URL-encoded ID the projects API accepts.
+pub fn project_id(full_name: &str) -> String {
+    full_name.replace('/', "%2F")
+}
@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use crate::github::Repo;
+
+/// The viewer's repositories with the same affiliations `/user/repos` lists by default, 100
+/// per page, which is the most GitHub allows.
+pub const VIEWER_REPOS: &str = "query($cursor: String) {
+  viewer {
+    repositories(first: 100, after: $cursor, ownerAffiliations: [OWNER, COLLABORATOR, ORGANIZATION_MEMBER]) {
+      pageInfo { hasNextPage endCursor }
+      nodes {
+        name nameWithOwner isPrivate isArchived isFork isTemplate pushedAt createdAt diskUsage description
+        stargazerCount forkCount
+        defaultBranchRef { name }
+        repositoryTopics(first: 100) { nodes { topic { name } } }
+        issues(states: OPEN) { totalCount }
+      }
+    }
+  }
+}";
+
+/// A GraphQL response. GitHub answers `200 OK` with `errors` set for failed queries.
+#[derive(Deserialize, Debug)]
+pub struct Response {
+    pub data: Option<Data>,
+    pub errors: Option<Vec<ErrorMessage>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ErrorMessage {
+    pub message: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Data {
+    pub viewer: Viewer,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Viewer {
+    pub repositories: Connection,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Connection {
+    #[serde(rename = "pageInfo")]
+    pub page_info: PageInfo,
+    pub nodes: Vec<Node>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PageInfo {
+    pub has_next_page: bool,
+    pub end_cursor: Option<String>,
+}
+
+/// The fields of a GraphQL `Repository` that have a counterpart in [`Repo`].
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Node {
+    name: String,
+    name_with_owner: String,
+    is_private: bool,
+    is_archived: bool,
+    is_fork: bool,
+    is_template: bool,
+    pushed_at: Option<DateTime<Utc>>,
+    created_at: Option<DateTime<Utc>>,
+    /// In KB, like the REST `size`.
+    disk_usage: Option<u64>,
+    description: Option<String>,
+    stargazer_count: u64,
+    fork_count: u64,
+    /// Missing for repositories without any commits.
+    default_branch_ref: Option<BranchRef>,
+    repository_topics: Topics,
+    issues: Count,
+}
+
+#[derive(Deserialize, Debug)]
+struct BranchRef {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Topics {
+    nodes: Vec<TopicNode>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TopicNode {
+    topic: Topic,
+}
+
+#[derive(Deserialize, Debug)]
+struct Topic {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Count {
+    total_count: u64,
+}
+
+impl From<Node> for Repo {
+    fn from(node: Node) -> Self {
+        Repo {
+            name: node.name,
+            full_name: node.name_with_owner,
+            private: Some(node.is_private),
+            archived: Some(node.is_archived),
+            fork: Some(node.is_fork),
+            is_template: Some(node.is_template),
+            pushed_at: node.pushed_at,
+            created_at: node.created_at,
+            size: node.disk_usage,
+            topics: Some(node.repository_topics.nodes.into_iter().map(|node| node.topic.name).collect()),
+            description: node.description,
+            default_branch: node.default_branch_ref.map(|branch| branch.name),
+            stargazers_count: Some(node.stargazer_count),
+            forks_count: Some(node.fork_count),
+            // Unlike the REST count, this leaves out open pull requests.
+            open_issues_count: Some(node.issues.total_count),
+        }
+    }
+}
+
+/// The GraphQL endpoint next to a REST base URL: `https://api.github.com/graphql`, or
+/// `https://HOST/api/graphql` for GitHub Enterprise Server's `https://HOST/api/v3`.
+pub fn endpoint(base_url: &str) -> String {
+    match base_url.strip_suffix("/v3") {
+        Some(api) => format!("{}/graphql", api),
+        None => format!("{}/graphql", base_url),
+    }
+}
@@ -0,0 +1,147 @@
+//! List and delete GitHub repositories in bulk, or GitLab projects with [`Provider::GitLab`].
+//!
+//! [`fetch_repos`] and [`delete_repos`] cover the common case:
+//!
+//! ```no_run
+//! # async fn run() -> repo_deleter::Result<()> {
+//! use std::time::Duration;
+//! use repo_deleter::github::DEFAULT_BASE_URL;
+//! use repo_deleter::{delete_repos, fetch_repos, Filters, GitHubClient, Listing, Provider};
+//!
+//! let token = std::env::var("GITHUB_TOKEN").unwrap();
+//! let base_url = DEFAULT_BASE_URL.to_string();
+//! let client = GitHubClient::new(token, base_url, Duration::from_secs(30), Provider::GitHub)?;
+//! let filters = Filters { forks_only: true, ..Filters::default() };
+//! let forks = fetch_repos(&client, Listing::Own, &filters).await?;
+//! for (repo, outcome) in delete_repos(&client, &forks).await {
+//!     println!("{}: {:?}", repo.full_name, outcome);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`GitHubClient`] has the individual requests for anything else, such as archiving.
+
+use std::time::Duration;
+use reqwest::StatusCode;
+
+pub mod error;
+pub mod filters;
+pub mod github;
+pub mod gitlab;
+mod graphql;
+
+pub use error::{Error, Result};
+pub use filters::{Filters, TopicMatch, Visibility};
+pub use github::{GitHubClient, Listing, Provider, Repo};
+
+/// How long [`fetch_repos`] waits for a rate limit to reset before giving up.
+pub const MAX_WAIT: Duration = Duration::from_secs(900);
+/// How many times [`fetch_repos`] retries a page that fails with a 5xx status or a network error.
+pub const MAX_RETRIES: u32 = 3;
+
+/// What happened to one repository passed to [`delete_repos`].
+#[derive(Debug)]
+pub enum Outcome {
+    Deleted,
+    /// The API answered 404, so it was most likely deleted already.
+    AlreadyGone,
+    Failed(Error),
+}
+
+/// Lists every repository selected by `listing` and keeps those matching `filters`. Fails if
+/// the listing stops before the last page, rather than returning part of it.
+pub async fn fetch_repos(client: &GitHubClient, listing: Listing<'_>, filters: &Filters) -> Result<Vec<Repo>> {
+    let listed = client.list_repos(listing, MAX_WAIT, MAX_RETRIES).await?;
+    if !listed.complete {
+        return Err(Error::Invalid(format!("The listing stopped after {} pages", listed.pages)));
+    }
+    Ok(listed.repos.into_iter().filter(|repo| filters.matches(repo)).collect())
+}
+
+/// Deletes `repos` one after another and reports the outcome of each, in order. Failed
+/// deletes are not retried.
+pub async fn delete_repos<'a>(client: &GitHubClient, repos: &'a [Repo]) -> Vec<(&'a Repo, Outcome)> {
+    // GitHub answers 204 No Content; GitLab answers 202 Accepted, as it deletes the project later.
+    let deleted = |status: StatusCode| match client.provider() {
+        Provider::GitLab => status == StatusCode::ACCEPTED,
+        Provider::GitHub => status == StatusCode::NO_CONTENT,
+    };
+    let mut outcomes = Vec::with_capacity(repos.len());
+    for repo in repos {
+        let outcome = match client.delete_repo(&repo.full_name).send().await {
+            Ok(response) if deleted(response.status()) => Outcome::Deleted,
+            Ok(response) if response.status() == StatusCode::NOT_FOUND => Outcome::AlreadyGone,
+            Ok(response) => {
+                let status = response.status();
+                let message = format!("Could not delete {}: {}", repo.full_name, github::error_message(response).await);
+                Outcome::Failed(Error::Status { status, message })
+            }
+            Err(e) => Outcome::Failed(e.into()),
+        };
+        outcomes.push((repo, outcome));
+    }
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn delete_repos_reports_each_outcome() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for (name, status) in [("done", 204), ("gone", 404), ("locked", 403), ("queued", 202)] {
+            Mock::given(method("DELETE"))
+                .and(path(format!("/repos/me/{}", name)))
+                .respond_with(ResponseTemplate::new(status))
+                .mount(&server)
+                .await;
+        }
+
+        let client =
+            GitHubClient::new("ghp_test".to_string(), server.uri(), Duration::from_secs(5), Provider::GitHub).unwrap();
+        let repos: Vec<Repo> = ["done", "gone", "locked", "queued"]
+            .iter()
+            .map(|name| {
+                serde_json::from_value(serde_json::json!({ "name": name, "full_name": format!("me/{}", name) })).unwrap()
+            })
+            .collect();
+        let outcomes = delete_repos(&client, &repos).await;
+        assert!(matches!(outcomes[0].1, Outcome::Deleted));
+        assert!(matches!(outcomes[1].1, Outcome::AlreadyGone));
+        assert!(matches!(outcomes[2].1, Outcome::Failed(Error::Status { status: StatusCode::FORBIDDEN, .. })));
+        // Only GitLab deletes in the background; GitHub's 202 is not trusted.
+        assert!(matches!(outcomes[3].1, Outcome::Failed(Error::Status { status: StatusCode::ACCEPTED, .. })));
+    }
+
+    #[tokio::test]
+    async fn gitlab_deletes_only_count_when_accepted() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for (name, status) in [("queued", 202), ("odd", 204)] {
+            Mock::given(method("DELETE"))
+                .and(path(format!("/projects/me%2F{}", name)))
+                .respond_with(ResponseTemplate::new(status))
+                .mount(&server)
+                .await;
+        }
+
+        let client =
+            GitHubClient::new("glpat-test".to_string(), server.uri(), Duration::from_secs(5), Provider::GitLab).unwrap();
+        let repos: Vec<Repo> = ["queued", "odd"]
+            .iter()
+            .map(|name| {
+                serde_json::from_value(serde_json::json!({ "name": name, "full_name": format!("me/{}", name) })).unwrap()
+            })
+            .collect();
+        let outcomes = delete_repos(&client, &repos).await;
+        assert!(matches!(outcomes[0].1, Outcome::Deleted));
+        assert!(matches!(outcomes[1].1, Outcome::Failed(Error::Status { status: StatusCode::NO_CONTENT, .. })));
+    }
+}
@@ -0,0 +1,143 @@
+use std::time::{Duration, Instant};
+use log::{debug, warn};
+use serde::Deserialize;
+
+const KEYRING_SERVICE: &str = "repo-deleter";
+/// Scopes requested for the OAuth token; `delete_repo` is what makes deletion possible.
+const SCOPES: &str = "repo delete_repo";
+
+#[derive(Deserialize, Debug)]
+struct DeviceCode {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    expires_in: u64,
+    interval: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    access_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+    interval: Option<u64>,
+}
+
+/// Returns the web URL that serves the OAuth endpoints for an API base URL: `github.com`
+/// for the public API, and the host itself for GitHub Enterprise Server (`/api/v3`).
+pub fn web_url(base_url: &str) -> String {
+    match base_url.strip_suffix("/api/v3") {
+        Some(host) => host.to_string(),
+        None if base_url == repo_deleter::github::DEFAULT_BASE_URL => "https://github.com".to_string(),
+        None => base_url.to_string(),
+    }
+}
+
+/// Runs GitHub's OAuth device flow: asks for a user code, lets `show` display it together
+/// with the verification URL, then polls until the user has approved the request in the
+/// browser. Returns the access token.
+pub async fn device_flow(
+    web_url: &str,
+    client_id: &str,
+    timeout: Duration,
+    proxy: Option<&str>,
+    show: impl FnOnce(&str, &str),
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut http = reqwest::Client::builder().timeout(timeout);
+    if let Some(url) = proxy {
+        http = http.proxy(repo_deleter::github::parse_proxy(url)?);
+    }
+    let http = http.build()?;
+    let code = http
+        .post(format!("{}/login/device/code", web_url))
+        .header(reqwest::header::ACCEPT, "application/json")
+        .form(&[("client_id", client_id), ("scope", SCOPES)])
+        .send()
+        .await
+        .map_err(|e| repo_deleter::github::describe_request_error(&e))?
+        .error_for_status()
+        .map_err(|e| format!("GitHub refused to start the device login, check --client-id: {}", e))?
+        .json::<DeviceCode>()
+        .await?;
+
+    show(&code.user_code, &code.verification_uri);
+
+    let deadline = Instant::now() + Duration::from_secs(code.expires_in);
+    let mut interval = Duration::from_secs(code.interval);
+    loop {
+        tokio::time::sleep(interval).await;
+        if Instant::now() > deadline {
+            return Err("The login code expired before it was approved".into());
+        }
+
+        let response = http
+            .post(format!("{}/login/oauth/access_token", web_url))
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&[
+                ("client_id", client_id),
+                ("device_code", code.device_code.as_str()),
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ])
+            .send()
+            .await
+            .map_err(|e| repo_deleter::github::describe_request_error(&e))?
+            .json::<TokenResponse>()
+            .await?;
+
+        if let Some(token) = response.access_token {
+            return Ok(token);
+        }
+        match response.error.as_deref() {
+            Some("authorization_pending") => debug!("Waiting for the login to be approved"),
+            // GitHub asks us to poll less often and says how long to wait from now on.
+            Some("slow_down") => {
+                interval = response.interval.map_or(interval + Duration::from_secs(5), Duration::from_secs)
+            }
+            Some("access_denied") => return Err("The login was denied in the browser".into()),
+            Some("expired_token") => return Err("The login code expired before it was approved".into()),
+            Some(error) => {
+                return Err(format!(
+                    "Device login failed: {}",
+                    response.error_description.as_deref().unwrap_or(error)
+                )
+                .into())
+            }
+            None => return Err("Device login failed: GitHub sent neither a token nor an error".into()),
+        }
+    }
+}
+
+/// Reads the token saved by `--login` for this GitHub host from the OS keychain. A missing
+/// entry or an unavailable keychain both mean there is no saved token.
+pub async fn load_token(web_url: &str) -> Option<String> {
+    // The keychain API blocks (on Linux it drives its own D-Bus connection), so keep it off
+    // the async threads.
+    let web_url = web_url.to_string();
+    tokio::task::spawn_blocking(move || load_token_blocking(&web_url)).await.ok().flatten()
+}
+
+fn load_token_blocking(web_url: &str) -> Option<String> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, web_url)
+        .map_err(|e| warn!("Cannot open the OS keychain: {}", e))
+        .ok()?;
+    match entry.get_password() {
+        Ok(token) => Some(token),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            debug!("No token read from the OS keychain: {}", e);
+            None
+        }
+    }
+}
+
+/// Saves the token in the OS keychain under this GitHub host, never in a plaintext file.
+pub async fn save_token(web_url: &str, token: &str) -> Result<(), String> {
+    let (web_url, token) = (web_url.to_string(), token.to_string());
+    tokio::task::spawn_blocking(move || {
+        keyring::Entry::new(KEYRING_SERVICE, &web_url)
+            .and_then(|entry| entry.set_password(&token))
+            .map_err(|e| format!("Failed to save the token in the OS keychain: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Failed to save the token in the OS keychain: {}", e))?
+}
@@ -1,106 +1,3652 @@
-use std::io::{self, Write};
-use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, USER_AGENT};
-use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use console::style;
+use env_logger::WriteStyle;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use globset::{GlobBuilder, GlobMatcher};
+use inquire::{InquireError, MultiSelect, Password, PasswordDisplayMode};
+use log::{debug, error, info, warn, LevelFilter};
+use regex::RegexBuilder;
+use reqwest::header::AUTHORIZATION;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
 
-#[derive(Deserialize, Debug)]
-struct Repo {
-    name: String,
+mod login;
+mod rules;
+
+use repo_deleter::github::{
+    describe_request_error, error_message, is_secondary_rate_limit, parse_proxy, rate_limit_wait, sso_url, GitHubClient, Listing, Provider, Repo, RepoList, User,
+    DEFAULT_BASE_URL,
+};
+use repo_deleter::{Error, Filters, TopicMatch, Visibility};
+use rules::Rules;
+
+/// List and delete your GitHub repositories in bulk.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// What to do; without one, the selected repositories are deleted as with `delete`
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Only list repositories whose full name (owner/name) matches this regex
+    #[arg(long, value_name = "REGEX")]
+    name_filter: Option<String>,
+
+    /// Match --name-filter case-insensitively
+    #[arg(long, requires = "name_filter")]
+    name_filter_ignore_case: bool,
+
+    /// Only list repositories whose last push is older than this, e.g. `180d`, `6m` or `2y`
+    #[arg(long, value_name = "DURATION")]
+    older_than: Option<String>,
+
+    /// Only list repositories created before this date (YYYY-MM-DD, UTC)
+    #[arg(long, value_name = "DATE")]
+    created_before: Option<String>,
+
+    /// Only list repositories created on or after this date (YYYY-MM-DD, UTC)
+    #[arg(long, value_name = "DATE")]
+    created_after: Option<String>,
+
+    /// Only list repositories GitHub reports as empty (size 0)
+    #[arg(long)]
+    empty_only: bool,
+
+    /// Only list repositories of at least this size in KB, as GitHub reports it
+    #[arg(long, value_name = "KB")]
+    min_size: Option<u64>,
+
+    /// Only list repositories of at most this size in KB
+    #[arg(long, value_name = "KB")]
+    max_size: Option<u64>,
+
+    /// Only list forks
+    #[arg(long)]
+    forks_only: bool,
+
+    /// Only list archived repositories
+    #[arg(long)]
+    archived_only: bool,
+
+    /// Only list repositories without a description
+    #[arg(long)]
+    no_description: bool,
+
+    /// Only list repositories without a README; costs one extra request per repository
+    #[arg(long)]
+    no_readme: bool,
+
+    /// Also list template repositories, which are left out by default
+    #[arg(long)]
+    include_templates: bool,
+
+    /// Leave out repositories pushed to within this many days; 0 turns the guard off
+    #[arg(long, default_value_t = 7, value_name = "DAYS")]
+    protect_recent: u64,
+
+    /// Also list repositories pushed to within --protect-recent days
+    #[arg(long)]
+    force: bool,
+
+    /// Only list repositories tagged with this GitHub topic; can be given more than once
+    #[arg(long)]
+    topic: Vec<String>,
+
+    /// With several --topic flags, whether a repository needs any or all of them
+    #[arg(long, value_enum, default_value_t = TopicMatch::Any)]
+    topic_match: TopicMatch,
+
+    /// Only list public or only private repositories
+    #[arg(long, value_enum, default_value_t = Visibility::All)]
+    visibility: Visibility,
+
+    /// Never list or touch this repository (owner/name); can be given more than once
+    #[arg(long, value_name = "FULL_NAME")]
+    exclude: Vec<String>,
+
+    /// How to order the listing; the numbers you type refer to this order
+    #[arg(long, value_enum, default_value_t = SortKey::Name)]
+    sort: SortKey,
+
+    /// Reverse the --sort order
+    #[arg(long)]
+    reverse: bool,
+
+    /// GitHub API base URL, e.g. https://github.example.com/api/v3 for GitHub Enterprise Server.
+    /// `${VAR}` is replaced with the environment variable VAR
+    #[arg(long, default_value = DEFAULT_BASE_URL, value_name = "URL")]
+    base_url: String,
+
+    /// Which API to talk to; with gitlab the base URL defaults to https://gitlab.com/api/v4
+    #[arg(long, value_enum, default_value_t = Provider::GitHub)]
+    provider: Provider,
+
+    /// List and delete repositories of this organization instead of your own. `${VAR}` is
+    /// replaced with the environment variable VAR
+    #[arg(long, value_name = "NAME")]
+    org: Option<String>,
+
+    /// List and delete repositories of this user instead of your own (only those the token can see)
+    #[arg(long, value_name = "LOGIN", conflicts_with = "org")]
+    owner: Option<String>,
+
+    /// List your repositories through the GraphQL API, which takes fewer requests on large
+    /// accounts; falls back to the REST listing if it fails
+    #[arg(long, conflicts_with_all = ["org", "owner"])]
+    graphql: bool,
+
+    /// Write the metadata of the selected repositories to this JSON file before deleting them
+    #[arg(long, value_name = "PATH")]
+    backup_file: Option<PathBuf>,
+
+    /// Append one JSON line per processed repository to this file
+    #[arg(long, value_name = "PATH")]
+    audit_log: Option<PathBuf>,
+
+    /// Write the final summary, in the --format, to this file instead of stdout; `-` is stdout
+    #[arg(long, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// POST a JSON summary of the run to this URL, e.g. a Slack or Discord webhook
+    #[arg(long, value_name = "URL")]
+    notify_webhook: Option<String>,
+
+    /// Cache the repository listing in this JSON file and reuse it on later runs
+    #[arg(long, value_name = "PATH")]
+    cache: Option<PathBuf>,
+
+    /// How long in seconds a cached listing stays valid
+    #[arg(long, default_value_t = 300, value_name = "SECONDS")]
+    cache_ttl: u64,
+
+    /// Ignore the cached listing and fetch a fresh one
+    #[arg(long, requires = "cache")]
+    refresh: bool,
+
+    /// Record when each successful run started in this JSON file, for --since-last-run
+    #[arg(long, value_name = "PATH")]
+    state_file: Option<PathBuf>,
+
+    /// Only consider repositories created since the last successful run in --state-file
+    #[arg(long, requires = "state_file")]
+    since_last_run: bool,
+
+    /// Per-request timeout in seconds
+    #[arg(long, default_value_t = 30, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: u64,
+
+    /// Send every request through this http://, https:// or socks5:// proxy; without it
+    /// HTTPS_PROXY, HTTP_PROXY and ALL_PROXY are used
+    #[arg(long, value_name = "URL")]
+    proxy: Option<String>,
+
+    /// How many times to retry a delete or listing page that fails with a 5xx status or a network error
+    #[arg(long, default_value_t = 3, value_name = "N")]
+    max_retries: u32,
+
+    /// Longest time in seconds to wait for a GitHub rate limit to reset
+    #[arg(long, default_value_t = 900, value_name = "SECONDS")]
+    max_wait: u64,
+
+    /// Start the requests that act on repositories at least this many milliseconds apart
+    #[arg(long, default_value_t = 0, value_name = "N")]
+    delay_ms: u64,
+
+    /// Output format; `json` prints a single summary object to stdout at the end of the run,
+    /// `jsonl` one line per repository as soon as it is processed
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Require retyping the full name of each selected private repository before deleting it
+    #[arg(long)]
+    strict_confirm: bool,
+
+    /// Skip selected repositories whose default branch is protected; costs one extra request per repository
+    #[arg(long)]
+    skip_protected: bool,
+
+    /// Check that each repository still exists right before acting on it and skip it if not;
+    /// costs one extra request per repository
+    #[arg(long)]
+    verify_before_delete: bool,
+
+    /// Before acting, ask to type this word to confirm
+    #[arg(long, value_name = "WORD")]
+    confirm_word: Option<String>,
+
+    /// Skip the [y/N] question asked before acting on a --from-file, --stdin, --rules or
+    /// --repo selection; with --name-filter, select every match instead of prompting
+    #[arg(long)]
+    yes: bool,
+
+    /// Before acting, ask to retype a random code shown at the prompt
+    #[arg(long, conflicts_with = "confirm_word")]
+    random_confirm: bool,
+
+    /// Ask about each selected repository in turn: y(es), N(o), a(ll) for the rest or q(uit)
+    #[arg(long, conflicts_with_all = ["confirm_word", "random_confirm", "stdin"])]
+    per_repo_confirm: bool,
+
+    /// Warn about selected repositories with more stars than this; --strict-confirm also asks
+    /// to retype their names
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    star_warn_threshold: u64,
+
+    /// Warn about selected repositories with open pull requests from outside contributors;
+    /// --strict-confirm also asks to retype their names. Costs one extra request per repository
+    #[arg(long)]
+    warn_open_prs: bool,
+
+    /// Warn about selected repositories that serve a published GitHub Pages site;
+    /// --strict-confirm also asks to retype their names. Costs one extra request per repository
+    #[arg(long)]
+    warn_pages: bool,
+
+    /// Also warn about selected repositories whose name matches this glob, like the built-in
+    /// profile README and .github checks; can be given more than once
+    #[arg(long, value_name = "GLOB")]
+    protect_pattern: Vec<String>,
+
+    /// Show visibility, size, open issues and last push next to each listed repository
+    #[arg(long)]
+    detailed: bool,
+
+    /// Pick repositories from a checkbox list instead of typing their numbers
+    #[arg(long)]
+    interactive: bool,
+
+    /// Select the repositories listed in this file (one full name per line) instead of prompting
+    #[arg(long, value_name = "PATH", conflicts_with = "interactive")]
+    from_file: Option<PathBuf>,
+
+    /// Act on just this repository (owner/name), fetched on its own instead of listing everything
+    #[arg(
+        long,
+        value_name = "FULL_NAME",
+        conflicts_with_all = [
+            "interactive", "from_file", "stdin", "rules", "list", "export_csv", "tokens_file", "cache", "graphql",
+            "org", "owner", "name_filter", "older_than", "created_before", "created_after", "min_size", "max_size",
+            "empty_only", "forks_only", "archived_only", "no_description", "no_readme", "topic", "visibility",
+            "since_last_run",
+        ]
+    )]
+    repo: Option<String>,
+
+    /// Like --from-file, but read the full names from stdin, e.g. piped from another tool
+    #[arg(long, conflicts_with_all = ["interactive", "from_file"])]
+    stdin: bool,
+
+    /// Select every repository of the --org, templates and recently pushed ones included,
+    /// instead of prompting, after typing the organization's name and the number of repositories
+    #[arg(
+        long,
+        requires = "i_understand_this_is_permanent",
+        conflicts_with_all = [
+            "interactive", "from_file", "stdin", "rules", "repo", "list", "export_csv", "name_filter", "older_than",
+            "created_before", "created_after", "min_size", "max_size", "empty_only", "forks_only", "archived_only",
+            "no_description", "no_readme", "topic", "visibility", "since_last_run", "exclude", "limit",
+        ]
+    )]
+    all: bool,
+
+    /// Acknowledge that --all acts on every repository of the organization
+    #[arg(long = "i-understand-this-is-permanent", requires = "all")]
+    i_understand_this_is_permanent: bool,
+
+    /// Select every repository matching the rules in this YAML file instead of prompting
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["interactive", "from_file", "stdin"])]
+    rules: Option<PathBuf>,
+
+    /// Act on at most this many of the selected repositories; 0 means no limit
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// Abort unless exactly this many repositories end up selected, after --limit
+    #[arg(long, value_name = "N")]
+    confirm_count: Option<usize>,
+
+    /// Archive the selected repositories instead of deleting them
+    #[arg(long)]
+    archive: bool,
+
+    /// Transfer the selected repositories to this user or organization instead of deleting them
+    #[arg(long, value_name = "LOGIN", conflicts_with = "archive")]
+    transfer_to: Option<String>,
+
+    /// Rename the selected repositories to this prefix followed by their name instead of deleting them
+    #[arg(long, value_name = "STR", conflicts_with_all = ["archive", "transfer_to"])]
+    rename_prefix: Option<String>,
+
+    /// Archive the selected repositories first and delete them only after a grace period
+    #[arg(long, conflicts_with_all = ["archive", "transfer_to", "rename_prefix"])]
+    safe_delete: bool,
+
+    /// Keep the selected repositories but replace their default branch with an empty commit;
+    /// needs --strict-confirm and --yes
+    #[arg(
+        long,
+        requires_all = ["strict_confirm", "yes"],
+        conflicts_with_all = ["archive", "transfer_to", "rename_prefix", "safe_delete"]
+    )]
+    clear_default_branch: bool,
+
+    /// Keep the selected repositories but take down their GitHub Pages site
+    #[arg(long, conflicts_with_all = ["archive", "transfer_to", "rename_prefix", "safe_delete", "clear_default_branch"])]
+    disable_pages: bool,
+
+    /// Seconds to wait between archiving and deleting with --safe-delete; Ctrl-C aborts
+    #[arg(long, default_value_t = 60, value_name = "SECONDS")]
+    grace: u64,
+
+    /// Show debug diagnostics such as request timings (same as RUST_LOG=debug)
+    #[arg(long)]
+    verbose: bool,
+
+    /// Print nothing but errors and prompts, e.g. for cron jobs; the exit code tells how it went
+    #[arg(long, conflicts_with_all = ["verbose", "list", "detailed", "interactive"])]
+    quiet: bool,
+
+    /// When to color warnings and successes; `auto` colors terminals unless NO_COLOR is set
+    #[arg(long, value_enum, default_value_t = Color::Auto)]
+    color: Color,
+
+    /// Print how long listing and each request took at the end of the run
+    #[arg(long)]
+    stats: bool,
+
+    /// Print the requests that would be sent, with the token redacted, without sending them
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Write the listed repositories to this CSV file and exit without deleting anything
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["interactive", "from_file", "stdin"])]
+    export_csv: Option<PathBuf>,
+
+    /// Print the listed repositories and exit without prompting or deleting anything
+    #[arg(long, conflicts_with_all = ["interactive", "from_file", "stdin", "export_csv"])]
+    list: bool,
+
+    /// Log in through the browser with GitHub's device flow and save the token in the OS keychain
+    #[arg(long)]
+    login: bool,
+
+    /// Read the token from this file, e.g. a mounted secret, instead of asking for it
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["login", "tokens_file"])]
+    token_file: Option<PathBuf>,
+
+    /// Process every account whose token is in this file (one per line), one after another
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["login", "org", "owner", "cache", "export_csv"])]
+    tokens_file: Option<PathBuf>,
+
+    /// Fetch the current listing and report which repositories of this --backup-file are gone
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["tokens_file", "export_csv", "list"])]
+    compare_backup: Option<PathBuf>,
+
+    /// Client ID of the GitHub OAuth app used by --login
+    #[arg(long, value_name = "ID")]
+    client_id: Option<String>,
+
+    /// Read default flag values from this TOML file instead of ~/.repo-deleter.toml
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Print a completion script for this shell to stdout and exit
+    #[arg(long, value_name = "SHELL", hide = true)]
+    completions: Option<Shell>,
+}
+
+/// Every flag is accepted before or after the subcommand.
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Print the listed repositories and exit; never deletes anything (same as --list)
+    List,
+    /// Pick repositories and delete them, or archive, transfer, rename, clear or unpublish them
+    /// with the matching flag
+    Delete,
+    /// Write the listed repositories to a CSV file and exit (same as --export-csv)
+    Export {
+        /// The CSV file to write
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+    },
+}
+
+/// Default flag values read from the config file. Keys mirror the `Args` fields; the token is
+/// deliberately not accepted here so it never ends up in a plaintext file.
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    name_filter: Option<String>,
+    name_filter_ignore_case: Option<bool>,
+    older_than: Option<String>,
+    created_before: Option<String>,
+    created_after: Option<String>,
+    empty_only: Option<bool>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    forks_only: Option<bool>,
+    include_templates: Option<bool>,
+    protect_recent: Option<u64>,
+    archived_only: Option<bool>,
+    no_description: Option<bool>,
+    no_readme: Option<bool>,
+    topic: Option<Vec<String>>,
+    topic_match: Option<TopicMatch>,
+    visibility: Option<Visibility>,
+    exclude: Option<Vec<String>>,
+    sort: Option<SortKey>,
+    reverse: Option<bool>,
+    base_url: Option<String>,
+    provider: Option<Provider>,
+    org: Option<String>,
+    owner: Option<String>,
+    graphql: Option<bool>,
+    backup_file: Option<PathBuf>,
+    audit_log: Option<PathBuf>,
+    output: Option<PathBuf>,
+    notify_webhook: Option<String>,
+    cache: Option<PathBuf>,
+    cache_ttl: Option<u64>,
+    state_file: Option<PathBuf>,
+    since_last_run: Option<bool>,
+    timeout: Option<u64>,
+    proxy: Option<String>,
+    max_retries: Option<u32>,
+    max_wait: Option<u64>,
+    delay_ms: Option<u64>,
+    format: Option<OutputFormat>,
+    strict_confirm: Option<bool>,
+    per_repo_confirm: Option<bool>,
+    skip_protected: Option<bool>,
+    verify_before_delete: Option<bool>,
+    confirm_word: Option<String>,
+    random_confirm: Option<bool>,
+    star_warn_threshold: Option<u64>,
+    warn_open_prs: Option<bool>,
+    warn_pages: Option<bool>,
+    protect_pattern: Option<Vec<String>>,
+    detailed: Option<bool>,
+    interactive: Option<bool>,
+    from_file: Option<PathBuf>,
+    rules: Option<PathBuf>,
+    limit: Option<usize>,
+    archive: Option<bool>,
+    transfer_to: Option<String>,
+    rename_prefix: Option<String>,
+    safe_delete: Option<bool>,
+    disable_pages: Option<bool>,
+    grace: Option<u64>,
+    verbose: Option<bool>,
+    quiet: Option<bool>,
+    color: Option<Color>,
+    stats: Option<bool>,
+    dry_run: Option<bool>,
+    client_id: Option<String>,
+}
+
+impl Config {
+    /// Fills in every field of `args` that was not given on the command line or through an
+    /// environment variable.
+    fn apply(self, args: &mut Args, matches: &ArgMatches) {
+        let unset = |id: &str| {
+            !matches!(matches.value_source(id), Some(ValueSource::CommandLine | ValueSource::EnvVariable))
+        };
+
+        macro_rules! merge {
+            ($($field:ident),* ; $($optional:ident),*) => {
+                $(if let Some(value) = self.$field {
+                    if unset(stringify!($field)) {
+                        args.$field = value;
+                    }
+                })*
+                $(if let Some(value) = self.$optional {
+                    if unset(stringify!($optional)) {
+                        args.$optional = Some(value);
+                    }
+                })*
+            };
+        }
+
+        merge!(
+            name_filter_ignore_case, empty_only, forks_only, include_templates, protect_recent, archived_only, no_description, no_readme, topic, topic_match, visibility, sort, reverse, base_url, provider, cache_ttl, since_last_run, timeout, max_retries, max_wait, delay_ms, format,
+            strict_confirm, per_repo_confirm, skip_protected, verify_before_delete, graphql, random_confirm, star_warn_threshold, warn_open_prs, warn_pages, detailed, interactive, archive, safe_delete, disable_pages, grace, verbose, quiet, color, stats, dry_run;
+            name_filter, older_than, created_before, created_after, min_size, max_size, confirm_word, org, owner, backup_file, audit_log, output, notify_webhook, proxy, cache, state_file, from_file, rules, limit, transfer_to, rename_prefix, client_id
+        );
+
+        // Exclusions and protected patterns are a safety net, so the config file's lists are
+        // kept even when more are given on the command line.
+        if let Some(exclude) = self.exclude {
+            args.exclude.extend(exclude);
+        }
+        if let Some(patterns) = self.protect_pattern {
+            args.protect_pattern.extend(patterns);
+        }
+    }
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    Text,
+    Json,
+    /// One JSON object per processed repository, printed as soon as it is done.
+    Jsonl,
+}
+
+/// Set from `--quiet` at startup.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// The `--output` file, opened at startup. Unset when the summary goes to stdout.
+static OUTPUT: OnceLock<(PathBuf, fs::File)> = OnceLock::new();
+
+/// Creates the `--output` file, and any missing parent directories, before anything is asked
+/// or changed.
+fn open_output(path: &Path) -> Result<(), String> {
+    if path == Path::new("-") {
+        return Ok(());
+    }
+    let failed = |e: io::Error| format!("Failed to create output {}: {}", path.display(), e);
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(failed)?;
+    }
+    let file = fs::File::create(path).map_err(failed)?;
+    let _ = OUTPUT.set((path.to_path_buf(), file));
+    Ok(())
+}
+
+/// Prints part of the final summary to stdout, or appends it to the `--output` file without
+/// colors.
+fn print_summary(text: &str) {
+    match OUTPUT.get() {
+        Some((path, file)) => {
+            if let Err(e) = (&*file).write_all(console::strip_ansi_codes(text).as_bytes()) {
+                error!("Failed to write output {}: {}", path.display(), e);
+            }
+        }
+        None => {
+            print!("{}", text);
+            let _ = io::stdout().flush();
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Prints text meant for a human. In the JSON modes this goes to stderr so that stdout
+    /// only carries JSON. `--quiet` drops it.
+    fn print_human(self, text: &str) {
+        if !quiet() {
+            self.print_prompt(text);
+        }
+    }
+
+    /// Like [`Self::print_human`], but kept with `--quiet`, since the run waits for an answer.
+    fn print_prompt(self, text: &str) {
+        match self {
+            OutputFormat::Text => {
+                print!("{}", text);
+                let _ = io::stdout().flush();
+            }
+            OutputFormat::Json | OutputFormat::Jsonl => eprint!("{}", text),
+        }
+    }
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Color {
+    Auto,
+    Always,
+    Never,
+}
+
+impl Color {
+    /// Switches colors on or off for stdout and stderr. `auto` leaves it to `console`, which
+    /// colors terminals only, after honouring `NO_COLOR`. JSON output is never colored, since
+    /// it is meant for scripts.
+    fn apply(self, format: OutputFormat) {
+        let enabled = match self {
+            _ if format != OutputFormat::Text => Some(false),
+            Color::Always => Some(true),
+            Color::Never => Some(false),
+            Color::Auto if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) => Some(false),
+            Color::Auto => None,
+        };
+        if let Some(enabled) = enabled {
+            console::set_colors_enabled(enabled);
+            console::set_colors_enabled_stderr(enabled);
+        }
+    }
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum SortKey {
+    /// Alphabetically, ignoring case.
+    Name,
+    /// Least recently pushed first.
+    Pushed,
+    /// Smallest first.
+    Size,
+    /// Oldest first.
+    Created,
+}
+
+impl SortKey {
+    /// Sorts the repositories in place. Repositories missing the field come first, and ties
+    /// are broken by full name so the order is stable between runs.
+    fn sort(self, repos: &mut [Repo], reverse: bool) {
+        repos.sort_by(|a, b| {
+            let order = match self {
+                SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                SortKey::Pushed => a.pushed_at.cmp(&b.pushed_at),
+                SortKey::Size => a.size.cmp(&b.size),
+                SortKey::Created => a.created_at.cmp(&b.created_at),
+            };
+            order.then_with(|| a.full_name.cmp(&b.full_name))
+        });
+        if reverse {
+            repos.reverse();
+        }
+    }
+}
+
+/// What to do with each selected repository.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Action {
+    Delete,
+    Archive,
+    /// Transfer to the given user or organization.
+    Transfer(String),
+    /// Rename to the given prefix followed by the current name.
+    Rename(String),
+    /// Replace the contents of the default branch with an empty commit.
+    Clear,
+    /// Take down the GitHub Pages site.
+    DisablePages,
+}
+
+impl Action {
+    fn from_args(args: &Args) -> Self {
+        if let Some(new_owner) = &args.transfer_to {
+            Action::Transfer(new_owner.clone())
+        } else if let Some(prefix) = &args.rename_prefix {
+            Action::Rename(prefix.clone())
+        } else if args.clear_default_branch {
+            Action::Clear
+        } else if args.disable_pages {
+            Action::DisablePages
+        } else if args.archive {
+            Action::Archive
+        } else {
+            Action::Delete
+        }
+    }
+
+    /// The repositories in `summary` this action went through for.
+    fn done<'a>(&self, summary: &'a Summary) -> &'a [String] {
+        match self {
+            Action::Delete => &summary.deleted,
+            Action::Archive => &summary.archived,
+            Action::Transfer(_) => &summary.transferred,
+            Action::Rename(_) => &summary.renamed,
+            Action::Clear => &summary.cleared,
+            Action::DisablePages => &summary.unpublished,
+        }
+    }
+
+    fn past_tense(&self) -> &'static str {
+        match self {
+            Action::Delete => "deleted",
+            Action::Archive => "archived",
+            Action::Transfer(_) => "transferred",
+            Action::Rename(_) => "renamed",
+            Action::Clear => "cleared",
+            Action::DisablePages => "unpublished",
+        }
+    }
+
+    fn verb(&self) -> &'static str {
+        match self {
+            Action::Delete => "delete",
+            Action::Archive => "archive",
+            Action::Transfer(_) => "transfer",
+            Action::Rename(_) => "rename",
+            Action::Clear => "clear",
+            Action::DisablePages => "unpublish",
+        }
+    }
+
+    /// Builds the request that performs the action. For `Clear` this moves the default branch
+    /// to `commit`, the empty commit created beforehand; dry runs create none and show a
+    /// placeholder instead.
+    fn request(&self, github: &GitHubClient, repo: &Repo, commit: Option<&str>) -> reqwest::RequestBuilder {
+        match self {
+            Action::Delete => github.delete_repo(&repo.full_name),
+            Action::Archive => github.archive_repo(&repo.full_name),
+            Action::Transfer(new_owner) => github.transfer_repo(&repo.full_name, new_owner),
+            Action::Rename(prefix) => github.rename_repo(&repo.full_name, &format!("{}{}", prefix, repo.name)),
+            Action::Clear => github.update_branch(
+                &repo.full_name,
+                repo.default_branch.as_deref().unwrap_or_default(),
+                commit.unwrap_or("<empty commit>"),
+            ),
+            Action::DisablePages => github.disable_pages(&repo.full_name),
+        }
+    }
+
+    /// Whether `status` means the action went through. GitHub answers a delete with
+    /// `204 No Content`, so anything else, including other 2xx codes, is not trusted. GitLab
+    /// only answers `202 Accepted`, as it may finish or delay the deletion in the background.
+    fn succeeded(&self, provider: Provider, status: StatusCode) -> bool {
+        match self {
+            Action::Delete if provider == Provider::GitLab => status == StatusCode::ACCEPTED,
+            Action::Delete => status == StatusCode::NO_CONTENT,
+            Action::Archive | Action::Transfer(_) | Action::Rename(_) | Action::Clear | Action::DisablePages => {
+                status.is_success()
+            }
+        }
+    }
+
+    /// Explains a `403 Forbidden` response for this action.
+    fn permission_hint(&self) -> &'static str {
+        match self {
+            Action::Delete => "permission denied, the token needs the delete_repo scope and admin rights on the repository",
+            Action::Archive | Action::Transfer(_) | Action::Rename(_) | Action::DisablePages => {
+                "permission denied, the token needs admin rights on the repository"
+            }
+            Action::Clear => "permission denied, the token needs write access to the repository contents",
+        }
+    }
+
+    /// Returns why the repository should be left alone, if the action would be a no-op.
+    fn skip_reason(&self, repo: &Repo) -> Option<&'static str> {
+        match self {
+            Action::Archive if repo.archived == Some(true) => Some("already archived"),
+            Action::Clear if repo.archived == Some(true) => Some("archived repositories are read-only"),
+            Action::Clear if repo.default_branch.is_none() => Some("no default branch"),
+            _ => None,
+        }
+    }
+}
+
+/// Builds the filters selected on the command line, validating the flags up front so mistakes
+/// surface before any request is made.
+fn filters_from_args(args: &Args) -> Result<Filters, String> {
+    let name = match &args.name_filter {
+        Some(pattern) => Some(
+            RegexBuilder::new(pattern)
+                .case_insensitive(args.name_filter_ignore_case)
+                .build()
+                .map_err(|e| format!("Invalid --name-filter pattern: {}", e))?,
+        ),
+        None => None,
+    };
+    let pushed_before = match &args.older_than {
+        Some(age) => {
+            let age = parse_age(age).map_err(|e| format!("Invalid --older-than value: {}", e))?;
+            Some(Utc::now() - age)
+        }
+        None => None,
+    };
+    let created_before = match &args.created_before {
+        Some(date) => Some(parse_date(date).map_err(|e| format!("Invalid --created-before value: {}", e))?),
+        None => None,
+    };
+    let created_after = match &args.created_after {
+        Some(date) => Some(parse_date(date).map_err(|e| format!("Invalid --created-after value: {}", e))?),
+        None => None,
+    };
+    // Checked here rather than by clap so that sizes from the config file are covered too.
+    if let (Some(min), Some(max)) = (args.min_size, args.max_size) {
+        if min > max {
+            return Err(format!("--min-size ({} KB) is larger than --max-size ({} KB)", min, max));
+        }
+    }
+    Ok(Filters {
+        name,
+        // Name globs only come from a --rules file.
+        include: Vec::new(),
+        exclude: Vec::new(),
+        pushed_before,
+        created_after,
+        created_before,
+        empty_only: args.empty_only,
+        min_size: args.min_size,
+        max_size: args.max_size,
+        forks_only: args.forks_only,
+        archived_only: args.archived_only,
+        no_description: args.no_description,
+        topics: args.topic.clone(),
+        topic_match: args.topic_match,
+        visibility: args.visibility,
+    })
+}
+
+/// Parses an age such as `90d`, `12w`, `6m` or `2y`. Months count as 30 days and years as 365.
+fn parse_age(value: &str) -> Result<chrono::Duration, String> {
+    let value = value.trim();
+    let split = value.len() - value.chars().last().map_or(0, char::len_utf8);
+    let (number, unit) = value.split_at(split);
+    let number = number
+        .parse::<u32>()
+        .map(i64::from)
+        .map_err(|_| format!("expected a number followed by d, w, m or y, got `{}`", value))?;
+    let days = match unit {
+        "d" => number,
+        "w" => number * 7,
+        "m" => number * 30,
+        "y" => number * 365,
+        _ => return Err(format!("unknown unit in `{}`, expected d, w, m or y", value)),
+    };
+    Ok(chrono::Duration::days(days))
+}
+
+/// Parses a `YYYY-MM-DD` date into midnight UTC at the start of that day.
+fn parse_date(value: &str) -> Result<DateTime<Utc>, String> {
+    let date = chrono::NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|e| format!("expected a date like 2024-03-01, got `{}` ({})", value, e))?;
+    Ok(date.and_time(chrono::NaiveTime::MIN).and_utc())
+}
+
+/// A repository listing saved by `--cache`, tagged with where it came from so a cache is
+/// never reused for a different account, organization or server.
+#[derive(Serialize, Deserialize, Debug)]
+struct RepoCache {
+    fetched_at: DateTime<Utc>,
+    /// The login of the token's account. Caches written before it was stored never match.
+    #[serde(default)]
+    login: String,
+    #[serde(default)]
+    provider: Option<Provider>,
+    base_url: String,
+    org: Option<String>,
+    #[serde(default)]
+    owner: Option<String>,
+    repos: Vec<Repo>,
+}
+
+/// The `--state-file`. The time is stored in UTC with an explicit offset, so the next run
+/// reads the same instant whatever its local timezone.
+#[derive(Serialize, Deserialize, Debug)]
+struct RunState {
+    last_run: DateTime<Utc>,
+}
+
+#[derive(Serialize, Default, Debug)]
+struct Summary {
+    /// The login of the account whose token was used; unset for the combination of several.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account: Option<String>,
+    deleted: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    archived: Vec<String>,
+    /// Repositories whose transfer GitHub accepted; transfers complete asynchronously.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    transferred: Vec<String>,
+    /// Repositories renamed with `--rename-prefix`, under their old full name.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    renamed: Vec<String>,
+    /// Repositories whose default branch was emptied with `--clear-default-branch`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cleared: Vec<String>,
+    /// Repositories whose Pages site was taken down with `--disable-pages`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unpublished: Vec<String>,
+    /// Repositories that a dry run would have acted on.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    dry_run: Vec<String>,
+    failed: Vec<FailedDelete>,
+    skipped: Vec<String>,
+    /// The summed `size` of the deleted repositories. Repositories without one count as 0.
+    reclaimed_kb: u64,
+    /// The error that stopped this account's run, with `--tokens-file`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip)]
+    stats: Option<Stats>,
+}
+
+/// The `--audit-log` file. Every processed repository is appended as one JSON line as soon
+/// as its request completes, so the record survives reruns and interrupted runs.
+struct AuditLog {
+    path: PathBuf,
+    file: fs::File,
+}
+
+#[derive(Serialize)]
+struct AuditEntry<'a> {
+    timestamp: DateTime<Utc>,
+    action: &'a str,
+    full_name: &'a str,
+    /// `succeeded`, `skipped` or `failed`, or `dry_run`, which only `--format jsonl` prints
+    /// since dry runs leave the audit log alone.
+    outcome: &'a str,
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+impl<'a> AuditEntry<'a> {
+    fn new(action: &'a Action, repo: &'a Repo, outcome: &'a str, status: Option<u16>, error: Option<&'a str>) -> Self {
+        AuditEntry { timestamp: Utc::now(), action: action.verb(), full_name: &repo.full_name, outcome, status, error }
+    }
+}
+
+impl AuditLog {
+    fn open(path: &Path) -> Result<Self, String> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open audit log {}: {}", path.display(), e))?;
+        Ok(AuditLog { path: path.to_path_buf(), file })
+    }
+
+    fn record(&self, entry: &AuditEntry) {
+        let result = serde_json::to_string(entry)
+            .map_err(io::Error::from)
+            .and_then(|line| writeln!(&self.file, "{}", line));
+        if let Err(e) = result {
+            error!("Failed to write audit log {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Timings collected for `--stats`.
+#[derive(Debug)]
+struct Stats {
+    started: Instant,
+    /// How long fetching the listing took and how many pages it had; `None` when it came
+    /// from the cache.
+    listing: Option<(Duration, u32)>,
+    /// How long each repository's request took, retries included.
+    latencies: Vec<Duration>,
+}
+
+impl Stats {
+    fn new(started: Instant) -> Self {
+        Stats { started, listing: None, latencies: Vec::new() }
+    }
+
+    fn report(&self) -> String {
+        let mut report = String::from("\nStats:\n");
+        match self.listing {
+            Some((elapsed, pages)) => {
+                report.push_str(&format!("  listing: {:.2?} for {} page(s)\n", elapsed, pages))
+            }
+            None => report.push_str("  listing: read from the cache\n"),
+        }
+        let mut latencies = self.latencies.clone();
+        latencies.sort();
+        if let (Some(min), Some(max)) = (latencies.first(), latencies.last()) {
+            report.push_str(&format!(
+                "  requests: {}, latency min {:.2?} / median {:.2?} / max {:.2?}\n",
+                latencies.len(),
+                min,
+                latencies[latencies.len() / 2],
+                max
+            ));
+        }
+        report.push_str(&format!("  total: {:.2?}\n", self.started.elapsed()));
+        report
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct FailedDelete {
     full_name: String,
+    status: Option<u16>,
+    error: String,
+}
+
+/// The process exit code, so wrapper scripts can tell outcomes apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Exit {
+    Success = 0,
+    /// At least one repository failed, or the run stopped on an error.
+    Failed = 1,
+    /// The token was rejected or lacks the rights for the requested organization.
+    Auth = 2,
+    /// No repositories were found or none matched the filters.
+    NoMatch = 3,
+    /// The user declined to continue or selected nothing.
+    Aborted = 4,
+    /// The command line or the config file is invalid; `EX_USAGE` from sysexits.h, since
+    /// clap's own 2 is taken by [`Exit::Auth`].
+    Usage = 64,
+    /// Ctrl-C stopped the run while repositories were being processed. 130 is what shells
+    /// report for a process killed by SIGINT.
+    Interrupted = 130,
+}
+
+/// An invalid command line or config file, which exits with [`Exit::Usage`].
+#[derive(thiserror::Error, Debug)]
+#[error("{0}")]
+struct UsageError(String);
+
+fn usage(e: impl ToString) -> UsageError {
+    UsageError(e.to_string())
+}
+
+/// Exits for a clap error: `--help` and `--version` with 0, anything else with [`Exit::Usage`].
+fn exit_on_clap_error(e: clap::Error) -> ! {
+    if !e.use_stderr() {
+        e.exit();
+    }
+    let _ = e.print();
+    std::process::exit(Exit::Usage as i32);
+}
+
+#[tokio::main]
+async fn main() {
+    let exit = match run().await {
+        Ok(exit) => exit,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            if matches!(e.downcast_ref::<Error>(), Some(Error::Auth(_))) {
+                Exit::Auth
+            } else if e.is::<UsageError>() {
+                Exit::Usage
+            } else {
+                Exit::Failed
+            }
+        }
+    };
+    std::process::exit(exit as i32);
+}
+
+async fn run() -> Result<Exit, Box<dyn std::error::Error>> {
+    let started = Instant::now();
+    listen_for_ctrl_c();
+    let args = parse_args().map_err(usage)?;
+    let format = args.format;
+    args.color.apply(format);
+    QUIET.store(args.quiet, Ordering::Relaxed);
+    init_logger(&args);
+    // Taken before the listing, so repositories created while this run goes on are still
+    // considered by the next one.
+    let run_started = Utc::now();
+    let mut filters = filters_from_args(&args).map_err(usage)?;
+    if let Some(path) = &args.rules {
+        Rules::read(path).and_then(|rules| rules.narrow(&mut filters)).map_err(usage)?;
+    }
+    if let (true, Some(path)) = (args.since_last_run, &args.state_file) {
+        match read_state(path)? {
+            Some(last_run) => {
+                info!("Only considering repositories created since the last run at {}", last_run.to_rfc3339());
+                filters.created_after = Some(filters.created_after.map_or(last_run, |after| after.max(last_run)));
+            }
+            None => info!("No earlier run recorded in {}, considering every repository", path.display()),
+        }
+    }
+    // clap only sees the flags; filters from the config file would narrow --all as well.
+    if args.all && (!filters.is_empty() || args.no_readme || !args.exclude.is_empty() || args.limit.is_some_and(|limit| limit > 0)) {
+        return Err(usage(
+            "--all acts on every repository of the organization, so it cannot be combined with filters, \
+             --exclude or --limit, also not from the config file",
+        )
+        .into());
+    }
+    let guards = Guards::from_args(&args).map_err(usage)?;
+    if let Some(path) = &args.output {
+        open_output(path)?;
+    }
+    let wanted_names = match &args.from_file {
+        None if args.repo.is_some() => Some(check_names(args.repo.iter().cloned().collect(), "--repo", args.provider)?),
+        Some(path) => Some(check_names(read_name_list(path)?, &path.display().to_string(), args.provider)?),
+        None if args.stdin => {
+            if io::stdin().is_terminal() {
+                return Err("--stdin needs the names piped in, e.g. `cat names.txt | repo-deleter --stdin`".into());
+            }
+            // Stdin holds the names, so nobody can answer the [y/N] question.
+            if !args.yes && !args.dry_run {
+                return Err(usage("--stdin cannot ask for confirmation, so it needs --yes, or --dry-run to try it").into());
+            }
+            let contents = io::read_to_string(io::stdin()).map_err(|e| format!("Failed to read stdin: {}", e))?;
+            Some(check_names(parse_name_list(&contents), "stdin", args.provider)?)
+        }
+        None => None,
+    };
+
+    let tokens = match &args.tokens_file {
+        Some(path) => {
+            let tokens = read_name_list(path)?;
+            if tokens.is_empty() {
+                return Err(format!("No tokens found in {}", path.display()).into());
+            }
+            tokens
+        }
+        None => vec![get_github_token(&args).await?],
+    };
+    // Every token is checked before any account is touched, so a stale token in the file
+    // stops the run up front instead of halfway through.
+    let mut accounts = Vec::new();
+    for (i, token) in tokens.into_iter().enumerate() {
+        let timeout = Duration::from_secs(args.timeout);
+        let github = GitHubClient::with_proxy(token, args.base_url.clone(), timeout, args.provider, args.proxy.as_deref())?;
+        let user = github.current_user().await.inspect_err(|_| {
+            if let Some(path) = &args.tokens_file {
+                error!("Token {} in {} was not accepted", i + 1, path.display());
+            }
+        })?;
+        accounts.push((github, user));
+    }
+
+    if let Some(path) = &args.compare_backup {
+        let (github, user) = &accounts[0];
+        format.print_human(&format!("Authenticated as {}\n", user.login));
+        return compare_backup(github, &args, path).await;
+    }
+
+    if let [(github, user)] = accounts.as_slice() {
+        let mut summary = Summary {
+            account: Some(user.login.clone()),
+            stats: args.stats.then(|| Stats::new(started)),
+            ..Summary::default()
+        };
+        return match run_account(github, user, &args, &filters, &guards, wanted_names.as_deref(), &mut summary).await? {
+            Some(exit) => {
+                if let Some(url) = &args.notify_webhook {
+                    notify_webhook(url, &args, &summary).await;
+                }
+                let exit = finish(format, &summary, exit)?;
+                save_state(&args, run_started, exit);
+                Ok(exit)
+            }
+            None => Ok(Exit::Success),
+        };
+    }
+
+    let mut summaries = Vec::new();
+    let mut exits = Vec::new();
+    for (github, user) in &accounts {
+        format.print_human(&format!("\n== {} ==\n", user.login));
+        let mut summary = Summary {
+            account: Some(user.login.clone()),
+            stats: args.stats.then(|| Stats::new(Instant::now())),
+            ..Summary::default()
+        };
+        // An error stops only this account, so what the others did is still reported.
+        match run_account(github, user, &args, &filters, &guards, wanted_names.as_deref(), &mut summary).await {
+            Ok(Some(exit)) => {
+                exits.push(exit);
+                summaries.push(summary);
+                if exit == Exit::Interrupted {
+                    break;
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!("Stopped {}: {}", user.login, e);
+                summary.error = Some(e.to_string());
+                exits.push(Exit::Failed);
+                summaries.push(summary);
+            }
+        }
+    }
+    if summaries.is_empty() {
+        return Ok(Exit::Success);
+    }
+    if let Some(url) = &args.notify_webhook {
+        notify_webhook(url, &args, &combine(&summaries)).await;
+    }
+    let exit = finish_accounts(format, &Action::from_args(&args), &summaries, &exits)?;
+    save_state(&args, run_started, exit);
+    Ok(exit)
+}
+
+/// Runs the listing, filtering, selection and processing for one account. Returns `None` when
+/// the run only printed or exported the listing, so there is no summary to report.
+async fn run_account(
+    github: &GitHubClient,
+    user: &User,
+    args: &Args,
+    filters: &Filters,
+    guards: &Guards,
+    wanted_names: Option<&[String]>,
+    summary: &mut Summary,
+) -> Result<Option<Exit>, Box<dyn std::error::Error>> {
+    let format = args.format;
+    format.print_human(&format!("Authenticated as {}\n", user.login));
+    // Only deleting needs delete_repo; listing, exporting and the other actions do not.
+    // --safe-delete is a delete too.
+    let deleting = args.export_csv.is_none() && !args.list && Action::from_args(args) == Action::Delete;
+    if deleting && !check_token_scopes(user, format)? {
+        format.print_human("Aborted.\n");
+        return Ok(Some(Exit::Aborted));
+    }
+
+    if let Some(org) = &args.org {
+        github.check_org_admin(org).await?;
+    }
+
+    // One request instead of the whole listing; the repository is then selected by name.
+    let single = match &args.repo {
+        Some(full_name) => match github.get_repo(full_name).await? {
+            Some(repo) => Some(vec![repo]),
+            None => {
+                format.print_human(&format!("{} was not found.\n", full_name));
+                return Ok(Some(Exit::NoMatch));
+            }
+        },
+        None => None,
+    };
+    let mut repos = match single.or_else(|| load_cache(args, &user.login)) {
+        Some(repos) => repos,
+        None => {
+            let listing_started = Instant::now();
+            let fetched = fetch_listing(github, args).await?;
+            if let Some(stats) = &mut summary.stats {
+                stats.listing = Some((listing_started.elapsed(), fetched.pages));
+            }
+            match &args.cache {
+                // A cached partial listing would hide the missing repositories on later runs too.
+                Some(_) if !fetched.complete => {
+                    warn!("Not updating the cache with an incomplete listing");
+                    fetched.repos
+                }
+                Some(path) => save_cache(path, args, &user.login, fetched.repos),
+                None => fetched.repos,
+            }
+        }
+    };
+    if let Some(owner) = &args.owner {
+        repos = check_owner(repos, owner);
+    }
+    if repos.is_empty() {
+        format.print_human("No repositories found.\n");
+        return Ok(Some(Exit::NoMatch));
+    }
+
+    if !filters.is_empty() {
+        let (matching, skipped): (Vec<Repo>, Vec<Repo>) = repos.into_iter().partition(|repo| filters.matches(repo));
+        summary.skipped = skipped.into_iter().map(|repo| repo.full_name).collect();
+        repos = matching;
+        if repos.is_empty() {
+            format.print_human("No repositories match the filters.\n");
+            return Ok(Some(Exit::NoMatch));
+        }
+    }
+
+    // Checked after the other filters so that they narrow down how many requests it takes.
+    if args.no_readme {
+        repos = keep_without_readme(github, repos, summary).await?;
+        if repos.is_empty() {
+            format.print_human("No repositories match the filters.\n");
+            return Ok(Some(Exit::NoMatch));
+        }
+    }
+
+    if !args.include_templates && !args.all {
+        let (templates, kept): (Vec<Repo>, Vec<Repo>) =
+            repos.into_iter().partition(|repo| repo.is_template == Some(true));
+        if !templates.is_empty() {
+            let names: Vec<&str> = templates.iter().map(|repo| repo.full_name.as_str()).collect();
+            format.print_human(&format!(
+                "Left out {} template repositories, pass --include-templates to list them: {}\n",
+                templates.len(),
+                names.join(", ")
+            ));
+        }
+        summary.skipped.extend(templates.into_iter().map(|repo| repo.full_name));
+        repos = kept;
+        if repos.is_empty() {
+            format.print_human("Only template repositories matched, see --include-templates.\n");
+            return Ok(Some(Exit::NoMatch));
+        }
+    }
+
+    if args.protect_recent > 0 && !args.force && !args.all {
+        let cutoff = Utc::now() - chrono::Duration::days(args.protect_recent.min(i64::MAX as u64) as i64);
+        let (recent, kept): (Vec<Repo>, Vec<Repo>) =
+            repos.into_iter().partition(|repo| repo.pushed_at.is_some_and(|pushed| pushed > cutoff));
+        if !recent.is_empty() {
+            let names: Vec<&str> = recent.iter().map(|repo| repo.full_name.as_str()).collect();
+            format.print_human(&format!(
+                "Protected {} repositories pushed to in the last {} days, pass --force to list them: {}\n",
+                recent.len(),
+                args.protect_recent,
+                names.join(", ")
+            ));
+        }
+        summary.skipped.extend(recent.into_iter().map(|repo| repo.full_name));
+        repos = kept;
+        if repos.is_empty() {
+            format.print_human("Only recently pushed repositories matched, see --protect-recent.\n");
+            return Ok(Some(Exit::NoMatch));
+        }
+    }
+
+    if !args.exclude.is_empty() {
+        let (excluded, kept): (Vec<Repo>, Vec<Repo>) = repos
+            .into_iter()
+            .partition(|repo| args.exclude.iter().any(|name| repo.full_name.eq_ignore_ascii_case(name)));
+        for repo in excluded {
+            format.print_human(&format!("Excluded {}\n", repo.full_name));
+            summary.skipped.push(repo.full_name);
+        }
+        repos = kept;
+        if repos.is_empty() {
+            format.print_human("No repositories left after --exclude.\n");
+            return Ok(Some(Exit::NoMatch));
+        }
+    }
+
+    args.sort.sort(&mut repos, args.reverse);
+
+    // Exported last, so the file holds exactly what a run with the same flags would offer.
+    if let Some(path) = &args.export_csv {
+        write_csv(path, &repos)?;
+        format.print_human(&format!("Wrote {} repositories to {}\n", repos.len(), path.display()));
+        return Ok(None);
+    }
+
+    // The listing is the result here, so it goes to --output like a summary would.
+    if args.list {
+        match format {
+            OutputFormat::Text => print_summary(&repo_listing(&repos, args.detailed)),
+            OutputFormat::Json => print_summary(&format!("{}\n", serde_json::to_string_pretty(&repos)?)),
+            OutputFormat::Jsonl => {
+                for repo in &repos {
+                    print_summary(&format!("{}\n", serde_json::to_string(repo)?));
+                }
+            }
+        }
+        return Ok(None);
+    }
+
+    let interactive = args.interactive && io::stdin().is_terminal();
+    if args.interactive && !interactive {
+        warn!("stdin is not a terminal, falling back to the text prompt");
+    }
+
+    let mut selected_repos = if let Some(names) = wanted_names {
+        select_by_name(&repos, names, format)
+    } else if args.rules.is_some() {
+        // The rules were applied as filters, so whatever is left is what they select.
+        let selected: Vec<&Repo> = repos.iter().collect();
+        print_selected(&selected, format);
+        selected
+    } else if args.all {
+        // Everything is shown first, since everything is about to be selected.
+        print_repos(&repos, format, args.detailed);
+        repos.iter().collect()
+    } else if args.name_filter.is_some() && args.yes {
+        // The regex already chose, e.g. `--name-filter '^tmp-' --yes` in a script.
+        print_repos(&repos, format, args.detailed);
+        repos.iter().collect()
+    } else if interactive {
+        select_interactively(&repos)?.into_iter().map(|i| &repos[i]).collect()
+    } else {
+        print_repos(&repos, format, args.detailed);
+        get_selected_repos(&repos, format)?
+    };
+    let checked;
+    let guards = if args.warn_open_prs || args.warn_pages {
+        let mut with_checks = guards.clone();
+        if args.warn_open_prs {
+            with_checks.open_prs = count_outside_prs(github, &selected_repos).await;
+        }
+        if args.warn_pages {
+            with_checks.pages = find_live_pages(github, &selected_repos).await;
+        }
+        checked = with_checks;
+        &checked
+    } else {
+        guards
+    };
+    guards.warn(&selected_repos, format);
+    if args.strict_confirm {
+        selected_repos = strict_confirm(selected_repos, guards, args.clear_default_branch, format, summary)?;
+    }
+    if args.skip_protected {
+        selected_repos = skip_protected(github, selected_repos, summary).await;
+    }
+    if let Some(limit) = args.limit.filter(|&limit| limit > 0 && limit < selected_repos.len()) {
+        for repo in selected_repos.split_off(limit) {
+            format.print_human(&format!("Deferred by --limit: {}\n", repo.full_name));
+            summary.skipped.push(repo.full_name.clone());
+        }
+    }
+
+    if let Some(expected) = args.confirm_count.filter(|&expected| expected != selected_repos.len()) {
+        error!(
+            "--confirm-count expected {} repositories, but {} are selected; nothing was changed",
+            expected,
+            selected_repos.len()
+        );
+        return Ok(Some(Exit::Aborted));
+    }
+
+    if selected_repos.is_empty() {
+        format.print_human("No repositories selected for deletion.\n");
+        return Ok(Some(Exit::Aborted));
+    }
+    if github.provider() == Provider::GitHub && !args.dry_run {
+        check_rate_budget(github, selected_repos.len(), args).await;
+    }
+
+    format.print_human(&format!("\n{}\n", preview(&Action::from_args(args), &selected_repos, format, args.dry_run)));
+
+    // Asked in dry runs too, so the answers can be tried out.
+    if args.per_repo_confirm {
+        selected_repos = confirm_each(selected_repos, &Action::from_args(args), format, summary)?;
+        if selected_repos.is_empty() {
+            format.print_human("No repositories confirmed, nothing was changed.\n");
+            return Ok(Some(Exit::Aborted));
+        }
+    }
+
+    // A dry run touches nothing, so there is nothing to confirm.
+    if let (false, true, Some(org)) = (args.dry_run, args.all, &args.org) {
+        if !confirm_all(args, org, selected_repos.len())? {
+            format.print_human("Confirmation did not match, nothing was changed.\n");
+            return Ok(Some(Exit::Aborted));
+        }
+    }
+    if !args.dry_run && !confirm_selection(args, selected_repos.len())? {
+        format.print_human("Confirmation did not match, nothing was changed.\n");
+        return Ok(Some(Exit::Aborted));
+    }
+
+    if let Some(path) = &args.backup_file {
+        // With several accounts, each one gets its own backup next to the given path.
+        let path = match (&args.tokens_file, &summary.account) {
+            (Some(_), Some(login)) => account_path(path, login),
+            _ => path.clone(),
+        };
+        write_backup(&path, &selected_repos, format)?;
+    }
+
+    // Opened before anything is touched, so an unwritable path stops the run instead of
+    // leaving operations unrecorded.
+    let audit = match &args.audit_log {
+        Some(path) if !args.dry_run => Some(AuditLog::open(path)?),
+        _ => None,
+    };
+    let audit = audit.as_ref();
+
+    let exit = if args.safe_delete {
+        safe_delete(github, selected_repos, args, audit, summary).await
+    } else {
+        process_repos(github, selected_repos, &Action::from_args(args), args, audit, summary).await
+    };
+
+    Ok(Some(exit))
+}
+
+/// Lists the repositories selected by the flags, through GraphQL with `--graphql`.
+async fn fetch_listing(github: &GitHubClient, args: &Args) -> Result<RepoList, repo_deleter::Error> {
+    let max_wait = Duration::from_secs(args.max_wait);
+    let listing = listing(args);
+    if args.graphql {
+        // Only reachable through the config file, since clap rejects the flags together.
+        if !matches!(listing, Listing::Own) {
+            warn!("--graphql only lists your own repositories; using the REST listing");
+        } else {
+            match github.list_repos_graphql(max_wait).await {
+                Ok(fetched) => return Ok(fetched),
+                Err(e) => warn!("GraphQL listing failed, falling back to REST: {}", e),
+            }
+        }
+    }
+    github.list_repos(listing, max_wait, args.max_retries).await
+}
+
+fn listing(args: &Args) -> Listing<'_> {
+    match (&args.org, &args.owner) {
+        (Some(org), _) => Listing::Org(org),
+        (None, Some(owner)) => Listing::User(owner),
+        (None, None) => Listing::Own,
+    }
+}
+
+#[derive(Serialize)]
+struct BackupComparison<'a> {
+    /// Backed-up repositories that no longer exist.
+    gone: Vec<&'a str>,
+    /// Backed-up repositories that are still listed.
+    remaining: Vec<&'a str>,
+}
+
+/// Compares a `--backup-file` with a fresh listing to confirm that an earlier run took effect.
+/// Exits with [`Exit::Failed`] when any backed-up repository still exists.
+async fn compare_backup(github: &GitHubClient, args: &Args, path: &Path) -> Result<Exit, Box<dyn std::error::Error>> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read backup {}: {}", path.display(), e))?;
+    let backup: Vec<Repo> =
+        serde_json::from_str(&contents).map_err(|e| format!("Invalid backup {}: {}", path.display(), e))?;
+
+    // Always a fresh listing; a cached one would report deleted repositories as remaining.
+    if let Some(org) = &args.org {
+        github.check_org_admin(org).await?;
+    }
+    let fetched = fetch_listing(github, args).await?;
+    // Anything missing from a partial listing would be reported as gone.
+    if !fetched.complete {
+        return Err("The listing is incomplete, so the backup cannot be compared with it".into());
+    }
+    let current = fetched.repos;
+
+    let (remaining, gone): (Vec<&Repo>, Vec<&Repo>) = backup
+        .iter()
+        .partition(|repo| current.iter().any(|r| r.full_name.eq_ignore_ascii_case(&repo.full_name)));
+    let comparison = BackupComparison {
+        gone: gone.iter().map(|repo| repo.full_name.as_str()).collect(),
+        remaining: remaining.iter().map(|repo| repo.full_name.as_str()).collect(),
+    };
+    match args.format {
+        // Like the tally, the report is left out with --quiet unless it goes to an --output file.
+        OutputFormat::Text if quiet() && OUTPUT.get().is_none() => {}
+        OutputFormat::Text => {
+            let mut report = format!("\nCompared {} backed-up repositories with the current listing:\n", backup.len());
+            for name in &comparison.gone {
+                report.push_str(&format!("  gone: {}\n", name));
+            }
+            for name in &comparison.remaining {
+                report.push_str(&format!("  still exists: {}\n", name));
+            }
+            report.push_str(&format!("\nGone {}, still existing {}\n", comparison.gone.len(), comparison.remaining.len()));
+            print_summary(&report);
+        }
+        OutputFormat::Json => print_summary(&format!("{}\n", serde_json::to_string_pretty(&comparison)?)),
+        OutputFormat::Jsonl => print_summary(&format!("{}\n", serde_json::to_string(&comparison)?)),
+    }
+
+    Ok(if comparison.remaining.is_empty() { Exit::Success } else { Exit::Failed })
+}
+
+/// The clap command for [`Args`], with every flag made global so that it can follow the
+/// subcommand too.
+fn command() -> clap::Command {
+    Args::command().mut_args(|arg| arg.global(true))
+}
+
+/// Parses the command line and fills in defaults from the config file. Precedence is
+/// command line, then config file, then built-in defaults.
+fn parse_args() -> Result<Args, Box<dyn std::error::Error>> {
+    let matches = command().try_get_matches().unwrap_or_else(|e| exit_on_clap_error(e));
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| exit_on_clap_error(e));
+    // The subcommands map onto the flags they stand for, which the rest of the run looks at.
+    match args.command.clone() {
+        Some(Command::List) if args.export_csv.is_some() => {
+            return Err("The list subcommand cannot be combined with --export-csv".into())
+        }
+        Some(Command::List) => args.list = true,
+        Some(Command::Export { .. }) if args.list => {
+            return Err("The export subcommand cannot be combined with --list".into())
+        }
+        Some(Command::Export { path }) => args.export_csv = Some(path),
+        Some(Command::Delete) | None => {}
+    }
+    // clap only checks --list's and --export-csv's conflicts when they are given as flags.
+    if args.command.is_some() && (args.list || args.export_csv.is_some()) {
+        if args.interactive {
+            return Err("--interactive only applies to the delete subcommand".into());
+        }
+        if args.from_file.is_some() {
+            return Err("--from-file only applies to the delete subcommand".into());
+        }
+        if args.stdin {
+            return Err("--stdin only applies to the delete subcommand".into());
+        }
+        if args.all {
+            return Err("--all only applies to the delete subcommand".into());
+        }
+    }
+
+    // Like --help, this exits before the config file is read, so a broken config cannot get
+    // in the way of installing completions.
+    if let Some(shell) = args.completions {
+        let mut command = command();
+        let name = command.get_name().to_string();
+        clap_complete::generate(shell, &mut command, name, &mut io::stdout());
+        std::process::exit(0);
+    }
+
+    let path = match &args.config {
+        Some(path) => Some(path.clone()),
+        None => default_config_path().filter(|path| path.exists()),
+    };
+    if let Some(path) = path {
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+        let config: Config = toml::from_str(&contents)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+        config.apply(&mut args, &matches);
+    }
+
+    // clap only checks conflicts between flags, so a config file could still select two
+    // actions, or be quiet and verbose at once.
+    let actions: Vec<&str> = [
+        ("--archive", args.archive),
+        ("--transfer-to", args.transfer_to.is_some()),
+        ("--rename-prefix", args.rename_prefix.is_some()),
+        ("--safe-delete", args.safe_delete),
+        ("--clear-default-branch", args.clear_default_branch),
+        ("--disable-pages", args.disable_pages),
+    ]
+    .into_iter()
+    .filter_map(|(flag, set)| set.then_some(flag))
+    .collect();
+    if let [first, second, ..] = actions[..] {
+        return Err(format!("{} cannot be combined with {} (check the config file)", first, second).into());
+    }
+    if args.quiet {
+        let noisy = [("--verbose", args.verbose), ("--detailed", args.detailed), ("--interactive", args.interactive)];
+        if let Some((flag, _)) = noisy.into_iter().find(|(_, set)| *set) {
+            return Err(format!("--quiet cannot be combined with {} (check the config file)", flag).into());
+        }
+    }
+
+    // The config file does not go through clap's value parsers, so repeat their checks.
+    if args.timeout == 0 {
+        return Err("Invalid timeout 0 in the config file: a request needs at least 1 second".into());
+    }
+    args.base_url = expand_env(&args.base_url).map_err(|e| format!("Invalid --base-url: {}", e))?;
+    if let Some(org) = &args.org {
+        args.org = Some(expand_env(org).map_err(|e| format!("Invalid --org: {}", e))?);
+    }
+    // Checked here rather than by clap, since --org may come from the config file.
+    if args.all && args.org.is_none() {
+        return Err("--all only works together with --org".into());
+    }
+    if args.provider == Provider::GitLab {
+        check_gitlab_args(&args)?;
+        if args.base_url == DEFAULT_BASE_URL {
+            args.base_url = Provider::GitLab.default_base_url().to_string();
+        }
+    }
+    args.base_url = normalize_base_url(&args.base_url)?;
+    if let Some(url) = &args.notify_webhook {
+        // Not echoed, since webhook URLs usually embed their secret.
+        reqwest::Url::parse(url).map_err(|e| format!("Invalid --notify-webhook URL: {}", e))?;
+    }
+    if let Some(url) = &args.proxy {
+        parse_proxy(url)?;
+    }
+    Ok(args)
+}
+
+/// Replaces every `${VAR}` in `value` with the environment variable VAR. A `$` not followed
+/// by `{` is kept as is.
+fn expand_env(value: &str) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let Some(len) = rest[start + 2..].find('}') else {
+            return Err(format!("unclosed ${{ in {}", value));
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        let var = std::env::var(name).map_err(|_| format!("environment variable {} is not set", name))?;
+        expanded.push_str(&var);
+        rest = &rest[start + 3 + len..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Rejects the flags that rely on GitHub-only APIs, before anything is fetched.
+fn check_gitlab_args(args: &Args) -> Result<(), String> {
+    let unsupported = [
+        ("--org", args.org.is_some()),
+        ("--owner", args.owner.is_some()),
+        ("--no-readme", args.no_readme),
+        ("--skip-protected", args.skip_protected),
+        ("--warn-open-prs", args.warn_open_prs),
+        ("--warn-pages", args.warn_pages),
+        ("--transfer-to", args.transfer_to.is_some()),
+        ("--rename-prefix", args.rename_prefix.is_some()),
+        ("--clear-default-branch", args.clear_default_branch),
+        ("--disable-pages", args.disable_pages),
+        ("--login", args.login),
+        ("--graphql", args.graphql),
+    ];
+    match unsupported.iter().find(|(_, given)| *given) {
+        Some((flag, _)) => Err(format!("{} is not supported with --provider gitlab", flag)),
+        None => Ok(()),
+    }
+}
+
+/// Checks that the base URL parses and strips trailing slashes so paths can be appended.
+fn normalize_base_url(base_url: &str) -> Result<String, String> {
+    let url = reqwest::Url::parse(base_url).map_err(|e| format!("Invalid --base-url {}: {}", base_url, e))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(format!("Invalid --base-url {}: expected an http or https URL", base_url));
+    }
+    Ok(base_url.trim_end_matches('/').to_string())
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(Path::new(&home).join(".repo-deleter.toml"))
+}
+
+/// Sends diagnostics to stderr through `log`. The default level is `info` (`warn` with
+/// `--format json` or `jsonl`, `debug` with `--verbose`) and can be overridden with `RUST_LOG`.
+fn init_logger(args: &Args) {
+    let level = if args.verbose {
+        LevelFilter::Debug
+    } else if args.quiet {
+        LevelFilter::Error
+    } else if args.format != OutputFormat::Text {
+        LevelFilter::Warn
+    } else {
+        LevelFilter::Info
+    };
+
+    env_logger::Builder::new()
+        .filter_level(level)
+        // The keychain crate logs every lookup at debug level, which only adds noise.
+        .filter_module("keyring", LevelFilter::Info)
+        .parse_default_env()
+        // Colors were already decided by --color; keep env_logger from stripping them again.
+        .write_style(if console::colors_enabled_stderr() { WriteStyle::Always } else { WriteStyle::Never })
+        .format(|buf, record| match record.level() {
+            log::Level::Info => writeln!(buf, "{}", record.args()),
+            level => {
+                let prefix = style(format!("{}:", level.as_str().to_lowercase())).for_stderr();
+                let prefix = if level <= log::Level::Warn { prefix.red() } else { prefix };
+                writeln!(buf, "{} {}", prefix, record.args())
+            }
+        })
+        .init();
+}
+
+/// Prints the JSON summary when requested; `--format jsonl` already printed every result.
+/// Any failed repository turns `exit` into [`Exit::Failed`].
+fn finish(format: OutputFormat, summary: &Summary, exit: Exit) -> Result<Exit, Box<dyn std::error::Error>> {
+    if let Some(stats) = &summary.stats {
+        // Goes to stderr so it never mixes with the JSON summary.
+        eprint!("{}", stats.report());
+    }
+    if format == OutputFormat::Json {
+        print_summary(&format!("{}\n", serde_json::to_string_pretty(summary)?));
+    }
+    // An interruption is reported as such even if some repositories failed before it.
+    if exit != Exit::Interrupted && !summary.failed.is_empty() {
+        return Ok(Exit::Failed);
+    }
+    Ok(exit)
+}
+
+/// Merges the summaries of several accounts into one, without stats.
+fn combine(summaries: &[Summary]) -> Summary {
+    Summary {
+        deleted: summaries.iter().flat_map(|s| s.deleted.iter().cloned()).collect(),
+        archived: summaries.iter().flat_map(|s| s.archived.iter().cloned()).collect(),
+        transferred: summaries.iter().flat_map(|s| s.transferred.iter().cloned()).collect(),
+        renamed: summaries.iter().flat_map(|s| s.renamed.iter().cloned()).collect(),
+        cleared: summaries.iter().flat_map(|s| s.cleared.iter().cloned()).collect(),
+        unpublished: summaries.iter().flat_map(|s| s.unpublished.iter().cloned()).collect(),
+        dry_run: summaries.iter().flat_map(|s| s.dry_run.iter().cloned()).collect(),
+        failed: summaries.iter().flat_map(|s| s.failed.iter().cloned()).collect(),
+        skipped: summaries.iter().flat_map(|s| s.skipped.iter().cloned()).collect(),
+        reclaimed_kb: summaries.iter().map(|s| s.reclaimed_kb).sum(),
+        ..Summary::default()
+    }
+}
+
+/// The `--notify-webhook` payload. `text` is what Slack shows and `content` what Discord
+/// shows; the rest is for anything else listening.
+#[derive(Serialize)]
+struct Notification<'a> {
+    text: &'a str,
+    content: &'a str,
+    #[serde(flatten)]
+    summary: &'a Summary,
+}
+
+/// Posts the summary to `url`. Best-effort: a failure is logged but does not change the
+/// outcome of the run.
+async fn notify_webhook(url: &str, args: &Args, summary: &Summary) {
+    let line = format!("repo-deleter: {}", tally_line(summary, &Action::from_args(args), summary.skipped.len()));
+    let notification = Notification { text: &line, content: &line, summary };
+    let mut http = reqwest::Client::builder();
+    // Already checked in parse_args.
+    if let Some(proxy) = args.proxy.as_deref().and_then(|url| parse_proxy(url).ok()) {
+        http = http.proxy(proxy);
+    }
+    let http = match http.build() {
+        Ok(http) => http,
+        Err(e) => {
+            warn!("Could not send the summary to the webhook: {}", e);
+            return;
+        }
+    };
+    let result = http
+        .post(url)
+        .timeout(Duration::from_secs(args.timeout))
+        .json(&notification)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    // reqwest includes the URL in its errors, and with it the webhook's secret.
+    match result {
+        Ok(_) => debug!("Sent the summary to the webhook"),
+        Err(e) => match e.status() {
+            Some(status) => warn!("The webhook answered {}, the summary was not delivered", status),
+            None => warn!("Could not reach the webhook: {}", e.without_url()),
+        },
+    }
+}
+
+#[derive(Serialize)]
+struct AccountsSummary<'a> {
+    accounts: &'a [Summary],
+    combined: &'a Summary,
+}
+
+/// Like [`finish`], for a run over several accounts: prints each account's stats, and the
+/// combined tally in text mode or every account's summary plus their combination in JSON.
+fn finish_accounts(
+    format: OutputFormat,
+    action: &Action,
+    summaries: &[Summary],
+    exits: &[Exit],
+) -> Result<Exit, Box<dyn std::error::Error>> {
+    for summary in summaries {
+        if let (Some(login), Some(stats)) = (&summary.account, &summary.stats) {
+            eprint!("\n{}:{}", login, stats.report());
+        }
+    }
+
+    let combined = combine(summaries);
+    match format {
+        OutputFormat::Text if quiet() && OUTPUT.get().is_none() => {}
+        OutputFormat::Text => {
+            print_summary(&format!("\n== All {} accounts ==\n", summaries.len()));
+            for summary in summaries {
+                if let (Some(login), Some(error)) = (&summary.account, &summary.error) {
+                    print_summary(&format!("{} stopped on an error: {}\n", login, error));
+                }
+            }
+            print_tally(&combined, action, !combined.dry_run.is_empty(), combined.skipped.len());
+        }
+        OutputFormat::Json => {
+            let output = AccountsSummary { accounts: summaries, combined: &combined };
+            print_summary(&format!("{}\n", serde_json::to_string_pretty(&output)?));
+        }
+        OutputFormat::Jsonl => {}
+    }
+
+    if exits.contains(&Exit::Interrupted) {
+        return Ok(Exit::Interrupted);
+    }
+    if !combined.failed.is_empty() || summaries.iter().any(|summary| summary.error.is_some()) {
+        return Ok(Exit::Failed);
+    }
+    // One account with nothing to do does not make the whole run unsuccessful.
+    if exits.contains(&Exit::Success) {
+        return Ok(Exit::Success);
+    }
+    Ok(exits[0])
+}
+
+/// Inserts the login before the extension, e.g. `backup.json` becomes `backup-alice.json`.
+fn account_path(path: &Path, login: &str) -> PathBuf {
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    let name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, login, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, login),
+    };
+    path.with_file_name(name)
+}
+
+fn prompt(message: &str, format: OutputFormat) -> Result<String, io::Error> {
+    format.print_prompt(message);
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().to_string())
+}
+
+/// Gets the token from `--token-file`, from `--login`, from the OS keychain where an earlier
+/// `--login` saved it, or by asking for it.
+async fn get_github_token(args: &Args) -> Result<String, Box<dyn std::error::Error>> {
+    if let Some(path) = &args.token_file {
+        return Ok(read_token_file(path)?);
+    }
+    let format = args.format;
+    let web_url = login::web_url(&args.base_url);
+    if args.login {
+        // There is no OAuth app registered for this tool, so the client ID has to be provided.
+        let client_id = args
+            .client_id
+            .as_deref()
+            .ok_or("--login needs the client ID of a GitHub OAuth app with device flow enabled, see --client-id")?;
+        let timeout = Duration::from_secs(args.timeout);
+        let token = login::device_flow(&web_url, client_id, timeout, args.proxy.as_deref(), |code, uri| {
+            format.print_human(&format!("Open {} and enter the code {}\nWaiting for approval...\n", uri, code))
+        })
+        .await?;
+        match login::save_token(&web_url, &token).await {
+            Ok(()) => info!("Saved the token in the OS keychain"),
+            Err(e) => warn!("{}, it is only used for this run", e),
+        }
+        return Ok(token);
+    }
+
+    if args.provider == Provider::GitLab {
+        return ask_token(args);
+    }
+    if let Some(token) = login::load_token(&web_url).await {
+        info!("Using the token saved by --login");
+        return Ok(token);
+    }
+    ask_token(args)
+}
+
+/// Reads a `--token-file`, ignoring surrounding whitespace such as the trailing newline.
+fn read_token_file(path: &Path) -> Result<String, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read token file {}: {}", path.display(), e))?;
+    match contents.trim() {
+        "" => Err(format!("The token file {} is empty", path.display())),
+        token => Ok(token.to_string()),
+    }
+}
+
+fn ask_token(args: &Args) -> Result<String, Box<dyn std::error::Error>> {
+    // Whatever was piped in has been read as the selection, so there is nobody to answer.
+    if args.stdin {
+        return Err("With --stdin the token has to come from --token-file, --tokens-file or an earlier --login".into());
+    }
+    let message = format!("Enter your {} token:", args.provider.name());
+    if io::stdin().is_terminal() {
+        // Nothing is echoed, so the token does not stay on screen or in a terminal recording.
+        let token = Password::new(&message)
+            .without_confirmation()
+            .with_display_mode(PasswordDisplayMode::Hidden)
+            .prompt()?;
+        return Ok(token.trim().to_string());
+    }
+    Ok(prompt(&format!("{} ", message), args.format)?)
+}
+
+/// Warns when the token is known to lack the `delete_repo` scope and asks whether to go on.
+/// Returns `false` if the user chose to abort.
+fn check_token_scopes(user: &User, format: OutputFormat) -> Result<bool, io::Error> {
+    let Some(scopes) = &user.scopes else {
+        format.print_human("Note: could not determine the token's scopes, deletions may fail with 403.\n");
+        return Ok(true);
+    };
+    if scopes.iter().any(|scope| scope == "delete_repo") {
+        return Ok(true);
+    }
+
+    format.print_human(&format!(
+        "Warning: the token's scopes ({}) do not include delete_repo, deletions will fail with 403.\n",
+        scopes.join(", ")
+    ));
+    let answer = prompt("Continue anyway? [y/N]: ", format)?;
+    Ok(answer.eq_ignore_ascii_case("y"))
+}
+
+/// Keeps the repositories that have no README, recording the others as skipped. This costs
+/// one request per repository.
+async fn keep_without_readme(
+    github: &GitHubClient,
+    repos: Vec<Repo>,
+    summary: &mut Summary,
+) -> Result<Vec<Repo>, Box<dyn std::error::Error>> {
+    info!("Checking {} repositories for a README...", repos.len());
+    let mut kept = Vec::new();
+    for repo in repos {
+        if github.has_readme(&repo.full_name).await? {
+            summary.skipped.push(repo.full_name);
+        } else {
+            kept.push(repo);
+        }
+    }
+    Ok(kept)
+}
+
+/// Warns when the hourly REST budget is smaller than what processing `count` repositories
+/// takes. Once it runs out, requests wait for the reset if it is within `--max-wait`, so this
+/// only says up front that the run will stall. Stays quiet if the budget cannot be read.
+async fn check_rate_budget(github: &GitHubClient, count: usize, args: &Args) {
+    // Clearing reads the branch and creates a commit before moving it, and --safe-delete
+    // archives before deleting.
+    let per_repo = 1
+        + u64::from(args.verify_before_delete)
+        + if args.clear_default_branch { 2 } else { 0 }
+        + u64::from(args.safe_delete);
+    let needed = count as u64 * per_repo;
+    match github.rate_limit().await {
+        Ok(budget) if budget.remaining < needed => {
+            let reset = DateTime::from_timestamp(budget.reset, 0)
+                .map_or_else(|| "the reset".to_string(), |reset| reset.format("%H:%M UTC").to_string());
+            warn!(
+                "This run takes about {} requests, but only {} of the {} per hour are left until {}; \
+                 the rest will wait for the reset if it is within --max-wait",
+                needed,
+                budget.remaining,
+                budget.limit,
+                reset
+            );
+        }
+        Ok(budget) => debug!("{} of {} requests left in the rate limit", budget.remaining, budget.limit),
+        Err(e) => debug!("{}", e),
+    }
+}
+
+/// Drops the repositories whose default branch is protected and records them as skipped.
+/// A repository whose protection cannot be checked is skipped as well, since it may be
+/// protected.
+async fn skip_protected<'a>(github: &GitHubClient, repos: Vec<&'a Repo>, summary: &mut Summary) -> Vec<&'a Repo> {
+    info!("Checking {} repositories for branch protection...", repos.len());
+    let mut kept = Vec::new();
+    for repo in repos {
+        // GitHub omits the default branch for some repositories that were never pushed to;
+        // there is nothing to protect in those.
+        let Some(branch) = &repo.default_branch else {
+            kept.push(repo);
+            continue;
+        };
+        match github.is_branch_protected(&repo.full_name, branch).await {
+            Ok(false) => kept.push(repo),
+            Ok(true) => {
+                info!("Skipped {}: default branch {} is protected", repo.full_name, branch);
+                summary.skipped.push(repo.full_name.clone());
+            }
+            Err(e) => {
+                warn!("Skipped {}: {}", repo.full_name, e);
+                summary.skipped.push(repo.full_name.clone());
+            }
+        }
+    }
+    kept
+}
+
+/// Counts the open pull requests from outside contributors of each selected repository, for
+/// `--warn-open-prs`. A repository that cannot be checked is left out with a warning.
+async fn count_outside_prs(github: &GitHubClient, repos: &[&Repo]) -> HashMap<String, usize> {
+    info!("Checking {} repositories for open pull requests...", repos.len());
+    let mut counts = HashMap::new();
+    for repo in repos {
+        match github.outside_pull_requests(&repo.full_name).await {
+            Ok(0) => {}
+            Ok(count) => {
+                counts.insert(repo.full_name.clone(), count);
+            }
+            Err(e) => warn!("{}: {}", repo.full_name, e),
+        }
+    }
+    counts
+}
+
+/// Finds the selected repositories that serve a published GitHub Pages site, for
+/// `--warn-pages`, with the site's URL. A repository that cannot be checked is left out with
+/// a warning.
+async fn find_live_pages(github: &GitHubClient, repos: &[&Repo]) -> HashMap<String, String> {
+    info!("Checking {} repositories for GitHub Pages sites...", repos.len());
+    let mut sites = HashMap::new();
+    for repo in repos {
+        match github.live_pages_url(&repo.full_name).await {
+            Ok(Some(url)) => {
+                sites.insert(repo.full_name.clone(), url);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("{}: {}", repo.full_name, e),
+        }
+    }
+    sites
+}
+
+/// Keeps only repositories actually owned by `owner` and warns when the listing looks
+/// incomplete. GitHub only returns another user's repositories that the token can see,
+/// which for most tokens means the public ones.
+fn check_owner(repos: Vec<Repo>, owner: &str) -> Vec<Repo> {
+    let total = repos.len();
+    let repos: Vec<Repo> = repos
+        .into_iter()
+        .filter(|repo| repo.full_name.split('/').next().is_some_and(|o| o.eq_ignore_ascii_case(owner)))
+        .collect();
+    if repos.len() < total {
+        warn!("Ignoring {} listed repositories that are not owned by {}", total - repos.len(), owner);
+    }
+    if repos.is_empty() {
+        warn!(
+            "GitHub returned no repositories for {}. Only repositories your token can see are listed, \
+             usually just the public ones; private repositories need a site admin token.",
+            owner
+        );
+    }
+    repos
+}
+
+/// Returns the cached listing if `--cache` points at a file that is younger than
+/// `--cache-ttl` and was fetched with the same base URL and organization.
+fn load_cache(args: &Args, login: &str) -> Option<Vec<Repo>> {
+    let path = args.cache.as_ref()?;
+    if args.refresh || !path.exists() {
+        return None;
+    }
+
+    let contents = fs::read_to_string(path)
+        .map_err(|e| warn!("Ignoring unreadable cache {}: {}", path.display(), e))
+        .ok()?;
+    let cache: RepoCache = serde_json::from_str(&contents)
+        .map_err(|e| warn!("Ignoring invalid cache {}: {}", path.display(), e))
+        .ok()?;
+
+    let age = Utc::now() - cache.fetched_at;
+    if cache.login != login
+        || cache.provider != Some(args.provider)
+        || cache.base_url != args.base_url
+        || cache.org != args.org
+        || cache.owner != args.owner
+        || age.num_seconds() > args.cache_ttl as i64
+    {
+        return None;
+    }
+    info!("Using cached listing from {} ({}s old)", path.display(), age.num_seconds());
+    Some(cache.repos)
+}
+
+/// Writes a fresh listing to the cache file and hands it back. Failing to write the cache
+/// only costs a refetch next time, so it is not fatal.
+fn save_cache(path: &Path, args: &Args, login: &str, repos: Vec<Repo>) -> Vec<Repo> {
+    let cache = RepoCache {
+        fetched_at: Utc::now(),
+        login: login.to_string(),
+        provider: Some(args.provider),
+        base_url: args.base_url.clone(),
+        org: args.org.clone(),
+        owner: args.owner.clone(),
+        repos,
+    };
+    match serde_json::to_string(&cache) {
+        Ok(json) => {
+            if let Err(e) = fs::write(path, json) {
+                warn!("Failed to write cache {}: {}", path.display(), e);
+            }
+        }
+        Err(e) => warn!("Failed to serialize cache: {}", e),
+    }
+    cache.repos
+}
+
+/// Reads when the last successful run started. A missing file means there was none; an
+/// unreadable one is an error, since ignoring it would widen the selection to everything.
+fn read_state(path: &Path) -> Result<Option<DateTime<Utc>>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read state file {}: {}", path.display(), e)),
+    };
+    let state: RunState =
+        serde_json::from_str(&contents).map_err(|e| format!("Invalid state file {}: {}", path.display(), e))?;
+    Ok(Some(state.last_run))
+}
+
+/// Records `started` in the `--state-file` after a run that went through, including one
+/// where nothing matched. Dry runs, aborted and failed runs leave it alone, so their
+/// repositories are considered again next time.
+fn save_state(args: &Args, started: DateTime<Utc>, exit: Exit) {
+    let Some(path) = &args.state_file else { return };
+    if args.dry_run || !matches!(exit, Exit::Success | Exit::NoMatch) {
+        return;
+    }
+    let result = serde_json::to_string(&RunState { last_run: started })
+        .map_err(io::Error::from)
+        .and_then(|json| fs::write(path, json));
+    if let Err(e) = result {
+        warn!("Failed to write state file {}: {}", path.display(), e);
+    }
+}
+
+fn print_repos(repos: &[Repo], format: OutputFormat, detailed: bool) {
+    format.print_human(&repo_listing(repos, detailed));
+}
+
+/// The numbered listing of `repos`, or the `--detailed` table.
+fn repo_listing(repos: &[Repo], detailed: bool) -> String {
+    let mut listing = String::from("\nYour repositories:\n");
+    if detailed {
+        listing.push_str(&detailed_listing(repos));
+    } else {
+        for (i, repo) in repos.iter().enumerate() {
+            listing.push_str(&format!("{}: {}\n", i + 1, repo.name));
+        }
+    }
+    listing
+}
+
+/// Renders the `--detailed` table. Columns are padded to their widest cell, and values
+/// GitHub did not report are shown as `-`.
+fn detailed_listing(repos: &[Repo]) -> String {
+    let unknown = || "-".to_string();
+    let mut rows = vec![["#", "NAME", "VISIBILITY", "SIZE", "ISSUES", "LAST PUSH"].map(String::from)];
+    for (i, repo) in repos.iter().enumerate() {
+        rows.push([
+            format!("{}:", i + 1),
+            repo.name.clone(),
+            repo.private.map_or_else(unknown, |private| if private { "private" } else { "public" }.to_string()),
+            // GitHub reports sizes in KB.
+            repo.size.map_or_else(unknown, |kb| format!("{:.1} MB", kb as f64 / 1024.0)),
+            repo.open_issues_count.map_or_else(unknown, |count| count.to_string()),
+            repo.pushed_at.map_or_else(unknown, |pushed_at| pushed_at.format("%Y-%m-%d").to_string()),
+        ]);
+    }
+
+    let mut widths = [0; 6];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut table = String::new();
+    for row in &rows {
+        let mut line = String::new();
+        for (column, (cell, width)) in row.iter().zip(widths).enumerate() {
+            // Numbers and sizes read best right-aligned.
+            let cell = match column {
+                0 | 3 | 4 => format!("{:>width$}", cell),
+                _ => format!("{:<width$}", cell),
+            };
+            line.push_str(&cell);
+            line.push_str("  ");
+        }
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+fn get_selected_repos(repos: &[Repo], format: OutputFormat) -> Result<Vec<&Repo>, io::Error> {
+    let names: Vec<&str> = repos.iter().map(|repo| repo.name.as_str()).collect();
+    loop {
+        let input = prompt(
+            "\nEnter the numbers or names of the repositories you want to delete (comma-separated, \
+             ranges like 3-5 and globs like old-* allowed): ",
+            format,
+        )?;
+
+        match parse_selection(&input, &names) {
+            Ok(indices) => return Ok(indices.into_iter().map(|i| &repos[i]).collect()),
+            Err(e) => format.print_human(&format!("{}, please try again.\n", e)),
+        }
+    }
+}
+
+/// Parses a selection such as `1,3-5,old-*` into zero-based indices into `names`, in the
+/// order given and without duplicates.
+///
+/// Numbers are one-based; `0` and numbers beyond the list are ignored, and a range reaching
+/// past the end is cut off there. A reversed range such as `5-3` selects nothing rather than
+/// being flipped, since it is more likely a typo than a deliberate choice. Any other item is
+/// a glob matched case-insensitively against the names, in listing order. Items made of
+/// digits and dashes that do not form a range, and invalid globs, are returned as an error.
+fn parse_selection(input: &str, names: &[&str]) -> Result<Vec<usize>, String> {
+    fn add(selected: &mut Vec<usize>, index: usize) {
+        if !selected.contains(&index) {
+            selected.push(index);
+        }
+    }
+
+    let mut selected = Vec::new();
+    let mut invalid = Vec::new();
+    for part in input.split(',') {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
+        }
+
+        if part.chars().all(|c| c.is_ascii_digit() || c == '-' || c.is_whitespace()) {
+            let range = match part.split_once('-') {
+                Some((start, end)) => start.trim().parse::<usize>().ok().zip(end.trim().parse::<usize>().ok()),
+                None => part.parse::<usize>().ok().map(|n| (n, n)),
+            };
+            match range {
+                Some((start, end)) => {
+                    for n in start.max(1)..=end.min(names.len()) {
+                        add(&mut selected, n - 1);
+                    }
+                }
+                None => invalid.push(part),
+            }
+            continue;
+        }
+
+        match GlobBuilder::new(part).case_insensitive(true).build() {
+            Ok(glob) => {
+                let matcher = glob.compile_matcher();
+                for (i, name) in names.iter().enumerate() {
+                    if matcher.is_match(name) {
+                        add(&mut selected, i);
+                    }
+                }
+            }
+            Err(_) => invalid.push(part),
+        }
+    }
+
+    if !invalid.is_empty() {
+        return Err(format!("Not a number, range or valid glob: {}", invalid.join(", ")));
+    }
+    Ok(selected)
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let token = get_github_token()?;
-    let client = reqwest::Client::new();
+/// Reads one repository full name per line, ignoring blank lines and `#` comments.
+fn read_name_list(path: &Path) -> Result<Vec<String>, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(parse_name_list(&contents))
+}
 
-    let repos = get_repos(&client, &token).await?;
-    if repos.is_empty() {
-        println!("No repositories found.");
-        return Ok(());
+fn parse_name_list(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Rejects the whole `--from-file` or `--stdin` list if any line is not a full name.
+fn check_names(names: Vec<String>, source: &str, provider: Provider) -> Result<Vec<String>, String> {
+    match names.iter().find(|name| !is_full_name(name, provider)) {
+        Some(name) => Err(format!("{} in {} is not an owner/name", name, source)),
+        None => Ok(names),
     }
+}
+
+/// Whether a `--from-file` line is a plain `owner/name`, or `group/subgroup/name` on GitLab,
+/// so it cannot smuggle `..`, a query or another path into a request.
+fn is_full_name(name: &str, provider: Provider) -> bool {
+    let segments: Vec<&str> = name.split('/').collect();
+    let shape = match provider {
+        Provider::GitHub => segments.len() == 2,
+        Provider::GitLab => segments.len() >= 2,
+    };
+    shape
+        && segments.iter().all(|segment| {
+            !matches!(*segment, "" | "." | "..")
+                && segment.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        })
+}
 
-    print_repos(&repos);
+/// Selects the repositories whose full name is in `names` and reports names that did not
+/// match any listed repository. GitHub treats names case-insensitively, so this does too.
+/// Only listed repositories can be selected, and requests use their `full_name` from the
+/// listing, never the text from the file.
+fn select_by_name<'a>(repos: &'a [Repo], names: &[String], format: OutputFormat) -> Vec<&'a Repo> {
+    fn owner(full_name: &str) -> &str {
+        full_name.rsplit_once('/').map_or(full_name, |(owner, _)| owner)
+    }
+    let mut selected: Vec<&Repo> = Vec::new();
+    for name in names {
+        match repos.iter().find(|repo| repo.full_name.eq_ignore_ascii_case(name)) {
+            Some(repo) if !selected.iter().any(|r| r.full_name == repo.full_name) => selected.push(repo),
+            Some(_) => {}
+            None if !repos.iter().any(|repo| owner(&repo.full_name).eq_ignore_ascii_case(owner(name))) => {
+                warn!("{} belongs to {}, whose repositories were not listed; ignoring it", name, owner(name))
+            }
+            None => warn!("{} was not found among the listed repositories", name),
+        }
+    }
 
-    let selected_repos = get_selected_repos(&repos)?;
+    print_selected(&selected, format);
+    selected
+}
 
-    if selected_repos.is_empty() {
-        println!("No repositories selected for deletion.");
-        return Ok(());
+fn print_selected(selected: &[&Repo], format: OutputFormat) {
+    let mut listing = String::from("\nSelected repositories:\n");
+    for repo in selected {
+        listing.push_str(&format!("{}\n", repo.full_name));
     }
+    format.print_human(&listing);
+}
 
-    delete_repos(&client, &token, selected_repos).await?;
+/// Shows a checkbox list of the repositories and returns the indices of the chosen ones.
+/// Pressing Esc selects nothing.
+fn select_interactively(repos: &[Repo]) -> Result<Vec<usize>, InquireError> {
+    let names: Vec<&str> = repos.iter().map(|repo| repo.name.as_str()).collect();
+    let result = MultiSelect::new("Select the repositories you want to delete:", names)
+        .with_help_message("space to toggle, type to filter, enter to confirm")
+        .with_page_size(15)
+        .raw_prompt();
 
-    Ok(())
+    match result {
+        Ok(selected) => Ok(selected.into_iter().map(|option| option.index).collect()),
+        Err(InquireError::OperationCanceled) => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
 }
 
-fn get_github_token() -> Result<String, io::Error> {
-    print!("Enter your GitHub token: ");
-    io::stdout().flush()?;
-    let mut token = String::new();
-    io::stdin().read_line(&mut token)?;
-    Ok(token.trim().to_string())
+/// The checks that single out selected repositories that are costly to lose: many stars, the
+/// special profile repositories, `--protect-pattern`, with `--warn-open-prs` open pull
+/// requests from outside contributors, and with `--warn-pages` published Pages sites. They
+/// get a loud warning and, with `--strict-confirm`, have to be confirmed by name.
+#[derive(Clone)]
+struct Guards {
+    star_threshold: u64,
+    patterns: Vec<(String, GlobMatcher)>,
+    /// Open pull requests from outside contributors by full name; only repositories that
+    /// have any are in here.
+    open_prs: HashMap<String, usize>,
+    /// The URLs of published GitHub Pages sites by full name.
+    pages: HashMap<String, String>,
 }
 
-async fn get_repos(client: &reqwest::Client, token: &str) -> Result<Vec<Repo>, reqwest::Error> {
-    let mut headers = HeaderMap::new();
-    headers.insert(ACCEPT, HeaderValue::from_static("application/vnd.github.v3+json"));
-    headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("token {}", token)).unwrap());
-    headers.insert(USER_AGENT, HeaderValue::from_static("repo-deleter"));
+impl Guards {
+    fn from_args(args: &Args) -> Result<Self, String> {
+        let patterns = args
+            .protect_pattern
+            .iter()
+            .map(|pattern| {
+                GlobBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map(|glob| (pattern.clone(), glob.compile_matcher()))
+                    .map_err(|e| format!("Invalid --protect-pattern {}: {}", pattern, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Guards {
+            star_threshold: args.star_warn_threshold,
+            patterns,
+            open_prs: HashMap::new(),
+            pages: HashMap::new(),
+        })
+    }
 
-    let repos = client
-        .get("https://api.github.com/user/repos")
-        .headers(headers)
-        .send()
-        .await?
-        .json::<Vec<Repo>>()
-        .await?;
+    /// Says why the repository is worth a warning, as the end of a sentence about it.
+    fn reason(&self, repo: &Repo) -> Option<String> {
+        let owner = repo.full_name.split('/').next().unwrap_or_default();
+        // A repository named after its owner holds the owner's profile README, and `.github`
+        // holds the profile and the default community files of an organization or user.
+        if repo.name.eq_ignore_ascii_case(owner) {
+            return Some(format!("holds the profile README of {}", owner));
+        }
+        if repo.name.eq_ignore_ascii_case(".github") {
+            return Some(format!("holds the profile and default community files of {}", owner));
+        }
+        if let Some((pattern, _)) = self.patterns.iter().find(|(_, matcher)| matcher.is_match(&repo.name)) {
+            return Some(format!("matches --protect-pattern {}", pattern));
+        }
+        if let Some(count) = self.open_prs.get(&repo.full_name) {
+            return Some(format!("has {} open pull requests from outside contributors that would be lost", count));
+        }
+        if let Some(url) = self.pages.get(&repo.full_name) {
+            return Some(format!("serves the GitHub Pages site {}, which would go offline", url));
+        }
+        // Repositories whose star count GitHub does not report are not treated as notable.
+        match repo.stargazers_count {
+            Some(stars) if stars > self.star_threshold => Some(format!(
+                "has {} stars and {} forks, other people may depend on it",
+                stars,
+                repo.forks_count.unwrap_or(0)
+            )),
+            _ => None,
+        }
+    }
 
-    Ok(repos)
+    /// Prints a bold warning for every selected repository that is worth one.
+    fn warn(&self, repos: &[&Repo], format: OutputFormat) {
+        for repo in repos {
+            let Some(reason) = self.reason(repo) else { continue };
+            let line = format!("Warning: {} {}", repo.full_name, reason);
+            let line = match format {
+                OutputFormat::Text => style(line).bold().red(),
+                OutputFormat::Json | OutputFormat::Jsonl => style(line).for_stderr().bold().red(),
+            };
+            format.print_human(&format!("{}\n", line));
+        }
+    }
 }
 
-fn print_repos(repos: &[Repo]) {
-    println!("\nYour repositories:");
-    for (i, repo) in repos.iter().enumerate() {
-        println!("{}: {}", i + 1, repo.name);
+/// Asks the user to retype the full name of every private repository and every repository
+/// the guards warned about, or of every repository when `clearing` default branches.
+/// Repositories whose name is not typed exactly are dropped from the selection and recorded
+/// as skipped.
+fn strict_confirm<'a>(
+    repos: Vec<&'a Repo>,
+    guards: &Guards,
+    clearing: bool,
+    format: OutputFormat,
+    summary: &mut Summary,
+) -> Result<Vec<&'a Repo>, io::Error> {
+    let mut confirmed = Vec::new();
+    for repo in repos {
+        let message = if clearing {
+            format!("Type the full name of {} to confirm clearing its default branch: ", repo.full_name)
+        } else if let Some(reason) = guards.reason(repo) {
+            format!("{} {}. Type its full name to confirm: ", repo.full_name, reason)
+        } else if repo.private == Some(true) {
+            format!("Type the full name of private repository {} to confirm: ", repo.full_name)
+        } else {
+            confirmed.push(repo);
+            continue;
+        };
+        if prompt(&message, format)? == repo.full_name {
+            confirmed.push(repo);
+        } else {
+            format.print_human(&format!("Name did not match, skipping {}\n", repo.full_name));
+            summary.skipped.push(repo.full_name.clone());
+        }
     }
+    Ok(confirmed)
 }
 
-fn get_selected_repos(repos: &[Repo]) -> Result<Vec<&Repo>, io::Error> {
-    print!("\nEnter the numbers of the repositories you want to delete (comma-separated): ");
-    io::stdout().flush()?;
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
+/// Counts the selection by visibility, e.g. `About to delete 12 public, 4 private, 2 forks`,
+/// so a filter mistake shows before the confirmation. The private count is highlighted.
+/// Forks are counted on top of their visibility.
+fn preview(action: &Action, repos: &[&Repo], format: OutputFormat, dry_run: bool) -> String {
+    let count = |wanted: Option<bool>| repos.iter().filter(|repo| repo.private == wanted).count();
+    let (public, private, unknown) = (count(Some(false)), count(Some(true)), count(None));
+    let forks = repos.iter().filter(|repo| repo.fork == Some(true)).count();
 
-    let selected_numbers: Vec<usize> = input
-        .trim()
-        .split(',')
-        .filter_map(|s| s.trim().parse::<usize>().ok())
-        .collect();
+    let mut groups = vec![format!("{} public", public)];
+    let private_group = format!("{} private", private);
+    groups.push(match format {
+        _ if private == 0 => private_group,
+        OutputFormat::Text => style(private_group).bold().red().to_string(),
+        OutputFormat::Json | OutputFormat::Jsonl => style(private_group).for_stderr().bold().red().to_string(),
+    });
+    if unknown > 0 {
+        groups.push(format!("{} of unknown visibility", unknown));
+    }
+    groups.push(format!("{} {}", forks, if forks == 1 { "fork" } else { "forks" }));
+    let lead = if dry_run { "Would" } else { "About to" };
+    format!("{} {} {}", lead, action.verb(), groups.join(", "))
+}
 
-    let selected_repos: Vec<&Repo> = selected_numbers
-        .into_iter()
-        .filter_map(|n| repos.get(n - 1))
-        .collect();
+/// Asks for `--confirm-word` or a fresh `--random-confirm` code before anything is touched.
+/// Without either, a selection that was not typed at the prompt gets a [y/N] question
+/// unless `--yes` or `--per-repo-confirm` is given. Returns `true` when nothing is asked.
+fn confirm_selection(args: &Args, count: usize) -> Result<bool, io::Error> {
+    let action = Action::from_args(args);
+    let by_name = args.from_file.is_some() || args.stdin || args.rules.is_some() || args.repo.is_some();
+    let word = match &args.confirm_word {
+        Some(word) => word.clone(),
+        None if args.random_confirm => random_code(),
+        None if by_name && !args.yes && !args.per_repo_confirm => {
+            let mut verb = action.verb().to_string();
+            verb[..1].make_ascii_uppercase();
+            let answer = prompt(&format!("\n{} {} repositories? [y/N] ", verb, count), args.format)?;
+            return Ok(matches!(answer.to_lowercase().as_str(), "y" | "yes"));
+        }
+        None => return Ok(true),
+    };
+    let message = format!("\nType {} to {} {} repositories: ", word, action.verb(), count);
+    Ok(prompt(&message, args.format)? == word)
+}
+
+/// Asks about each repository for `--per-repo-confirm`. `a` takes it and the rest without
+/// asking, `q` skips it and the rest, and anything but `y` skips just that one. Skipped
+/// repositories are recorded as such.
+fn confirm_each<'a>(
+    repos: Vec<&'a Repo>,
+    action: &Action,
+    format: OutputFormat,
+    summary: &mut Summary,
+) -> Result<Vec<&'a Repo>, io::Error> {
+    let mut confirmed = Vec::new();
+    let mut take_rest = false;
+    let mut repos = repos.into_iter();
+    for repo in repos.by_ref() {
+        let answer = prompt(&format!("{}: {}? [y/N/a/q] ", repo.full_name, action.verb()), format)?;
+        match answer.to_lowercase().as_str() {
+            "y" | "yes" => confirmed.push(repo),
+            "a" | "all" => {
+                confirmed.push(repo);
+                take_rest = true;
+                break;
+            }
+            "q" | "quit" => {
+                summary.skipped.push(repo.full_name.clone());
+                break;
+            }
+            _ => summary.skipped.push(repo.full_name.clone()),
+        }
+    }
+    // Anything left was not asked about because of an `a` or a `q`.
+    if take_rest {
+        confirmed.extend(repos);
+    } else {
+        summary.skipped.extend(repos.map(|repo| repo.full_name.clone()));
+    }
+    Ok(confirmed)
+}
+
+/// The two confirmations `--all` asks for before any other: the organization's name, then
+/// how many repositories are about to be changed, as shown by the preview.
+fn confirm_all(args: &Args, org: &str, count: usize) -> Result<bool, io::Error> {
+    let action = Action::from_args(args);
+    let message = format!(
+        "\nThis will {} every selected repository of {}. Type the organization's name to continue: ",
+        action.verb(),
+        org
+    );
+    if prompt(&message, args.format)? != org {
+        return Ok(false);
+    }
+    let message = format!("Type how many repositories will be {}: ", action.past_tense());
+    Ok(prompt(&message, args.format)?.parse::<usize>() == Ok(count))
+}
+
+/// A six-character code that changes every run, so it cannot be typed from habit. Letters
+/// and digits that look alike (0/O, 1/I/L) are left out.
+fn random_code() -> String {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    const ALPHABET: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+    // std's hasher keys are random per process, which is plenty for a typing check.
+    let mut bits = RandomState::new().build_hasher().finish();
+    (0..6)
+        .map(|_| {
+            let c = ALPHABET[(bits % ALPHABET.len() as u64) as usize] as char;
+            bits /= ALPHABET.len() as u64;
+            c
+        })
+        .collect()
+}
+
+fn write_backup(path: &Path, repos: &[&Repo], format: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let json = serde_json::to_string_pretty(repos)?;
+    fs::write(path, json)
+        .map_err(|e| format!("Failed to write backup to {}: {}", path.display(), e))?;
+    format.print_human(&format!("\nSaved backup of {} repositories to {}\n", repos.len(), path.display()));
+    Ok(())
+}
 
-    Ok(selected_repos)
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    name: &'a str,
+    full_name: &'a str,
+    private: Option<bool>,
+    archived: Option<bool>,
+    fork: Option<bool>,
 }
 
-async fn delete_repos(client: &reqwest::Client, token: &str, repos: Vec<&Repo>) -> Result<(), reqwest::Error> {
-    println!("\nDeleting selected repositories...");
+fn write_csv(path: &Path, repos: &[Repo]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer =
+        csv::Writer::from_path(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
     for repo in repos {
-        let mut headers = HeaderMap::new();
-        headers.insert(ACCEPT, HeaderValue::from_static("application/vnd.github.v3+json"));
-        headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("token {}", token)).unwrap());
-        headers.insert(USER_AGENT, HeaderValue::from_static("repo-deleter"));
+        writer.serialize(CsvRow {
+            name: &repo.name,
+            full_name: &repo.full_name,
+            private: repo.private,
+            archived: repo.archived,
+            fork: repo.fork,
+        })?;
+    }
+    writer.flush().map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+async fn process_repos(
+    github: &GitHubClient,
+    repos: Vec<&Repo>,
+    action: &Action,
+    args: &Args,
+    audit: Option<&AuditLog>,
+    summary: &mut Summary,
+) -> Exit {
+    let format = args.format;
+    // `--format jsonl` prints the same lines the audit log gets, without waiting for the end.
+    let record = |repo: &Repo, outcome: &str, status: Option<u16>, error: Option<&str>| {
+        let entry = AuditEntry::new(action, repo, outcome, status, error);
+        if let Some(audit) = audit {
+            audit.record(&entry);
+        }
+        if format == OutputFormat::Jsonl {
+            if let Ok(line) = serde_json::to_string(&entry) {
+                print_summary(&format!("{}\n", line));
+            }
+        }
+    };
+    let text = format == OutputFormat::Text;
+    if args.dry_run {
+        format.print_human("\nDry run, these requests would be sent:\n");
+    } else if text {
+        let doing = match action {
+            Action::Delete => "Deleting selected repositories".to_string(),
+            Action::Archive => "Archiving selected repositories".to_string(),
+            Action::Transfer(new_owner) => format!("Transferring selected repositories to {}", new_owner),
+            Action::Rename(prefix) => format!("Renaming selected repositories with prefix {}", prefix),
+            Action::Clear => "Clearing the default branch of selected repositories".to_string(),
+            Action::DisablePages => "Unpublishing the Pages sites of selected repositories".to_string(),
+        };
+        let account = summary.account.as_deref().map(|login| format!(" as {}", login)).unwrap_or_default();
+        format.print_human(&format!("\n{}{}...\n", doing, account));
+    }
+    let progress = Progress::new(repos.len(), action, args);
+    let mut throttle = Throttle::new(Duration::from_millis(args.delay_ms));
+    let skipped_before = summary.skipped.len();
+    let catch_ctrl_c = CatchCtrlC::start();
+    let mut remaining = repos.into_iter();
+    for repo in remaining.by_ref() {
+        if interrupted() {
+            summary.skipped.push(repo.full_name.clone());
+            break;
+        }
+        if let Some(reason) = action.skip_reason(repo) {
+            record(repo, "skipped", None, Some(reason));
+            progress.log(|| info!("Skipped {}: {}", repo.name, reason));
+            summary.skipped.push(repo.full_name.clone());
+            progress.inc();
+            continue;
+        }
+
+        // It may have been deleted since the listing, e.g. from another session.
+        if args.verify_before_delete && !args.dry_run {
+            match github.repo_exists(&repo.full_name).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    record(repo, "skipped", Some(404), Some("already gone"));
+                    progress.log(|| info!("Skipped {}: already gone", repo.name));
+                    summary.skipped.push(repo.full_name.clone());
+                    progress.inc();
+                    continue;
+                }
+                Err(e) => progress.log(|| warn!("{}; trying anyway", e)),
+            }
+        }
+
+        // Clearing takes an empty commit on top of the branch before the branch can be moved.
+        let mut commit = None;
+        if *action == Action::Clear && !args.dry_run {
+            let branch = repo.default_branch.as_deref().unwrap_or_default();
+            match github.create_empty_commit(&repo.full_name, branch).await {
+                Ok(sha) => commit = Some(sha),
+                Err(e) => {
+                    let error = e.to_string();
+                    record(repo, "failed", None, Some(&error));
+                    progress.log(|| error!("Failed to {} {}: {}", action.verb(), repo.name, error));
+                    summary.failed.push(FailedDelete { full_name: repo.full_name.clone(), status: None, error });
+                    progress.inc();
+                    continue;
+                }
+            }
+        }
+
+        let request = || action.request(github, repo, commit.as_deref());
+        if args.dry_run {
+            match request().build() {
+                Ok(request) => format.print_human(&format!("{}\n", curl_command(&request))),
+                Err(e) => format.print_human(&format!("Could not build request for {}: {}\n", repo.name, e)),
+            }
+            record(repo, "dry_run", None, None);
+            summary.dry_run.push(repo.full_name.clone());
+            continue;
+        }
+
+        let request_started = Instant::now();
+        let result = send_with_retry(request, &repo.name, args, &progress, &mut throttle).await;
+        if let Some(stats) = &mut summary.stats {
+            stats.latencies.push(request_started.elapsed());
+        }
+        let (status, error) = match result {
+            Ok(Sent::Response(response)) if action.succeeded(github.provider(), response.status()) => {
+                record(repo, "succeeded", Some(response.status().as_u16()), None);
+                match action {
+                    Action::Delete => {
+                        progress.log(|| info!("{}", success(format!("Successfully deleted {}", repo.name))));
+                        summary.deleted.push(repo.full_name.clone());
+                        summary.reclaimed_kb += repo.size.unwrap_or(0);
+                    }
+                    Action::Archive => {
+                        progress.log(|| info!("{}", success(format!("Archived: {}", repo.name))));
+                        summary.archived.push(repo.full_name.clone());
+                    }
+                    Action::Transfer(new_owner) => {
+                        // GitHub answers 202 Accepted and finishes the transfer in the background.
+                        progress.log(|| {
+                            info!("{}", success(format!("Transfer of {} to {} initiated", repo.name, new_owner)))
+                        });
+                        summary.transferred.push(repo.full_name.clone());
+                    }
+                    Action::Rename(prefix) => {
+                        progress.log(|| info!("{}", success(format!("Renamed {} to {}{}", repo.name, prefix, repo.name))));
+                        summary.renamed.push(repo.full_name.clone());
+                    }
+                    Action::Clear => {
+                        progress.log(|| info!("{}", success(format!("Cleared the default branch of {}", repo.name))));
+                        summary.cleared.push(repo.full_name.clone());
+                    }
+                    Action::DisablePages => {
+                        progress.log(|| info!("{}", success(format!("Unpublished the Pages site of {}", repo.name))));
+                        summary.unpublished.push(repo.full_name.clone());
+                    }
+                }
+                progress.inc();
+                continue;
+            }
+            Ok(Sent::Response(response)) if *action == Action::Delete && response.status() == StatusCode::NOT_FOUND => {
+                // Already deleted, possibly by an earlier run. GitHub also answers 404 for
+                // repositories the token cannot see, but those were listed with this token.
+                record(repo, "skipped", Some(404), Some("already gone"));
+                progress.log(|| info!("Skipped {}: already gone", repo.name));
+                summary.skipped.push(repo.full_name.clone());
+                progress.inc();
+                continue;
+            }
+            Ok(Sent::Response(response))
+                if *action == Action::DisablePages && response.status() == StatusCode::NOT_FOUND =>
+            {
+                // Nothing to take down; the repository never had a site or it is already gone.
+                record(repo, "skipped", Some(404), Some("no Pages site"));
+                progress.log(|| info!("Skipped {}: no Pages site", repo.name));
+                summary.skipped.push(repo.full_name.clone());
+                progress.inc();
+                continue;
+            }
+            Ok(Sent::Response(response))
+                if matches!(action, Action::Rename(_)) && response.status() == StatusCode::UNPROCESSABLE_ENTITY =>
+            {
+                // GitHub refuses the rename when the new name is already taken.
+                let reason = error_message(response).await;
+                record(repo, "skipped", Some(422), Some(&reason));
+                progress.log(|| info!("Skipped {}: {}", repo.name, reason));
+                summary.skipped.push(repo.full_name.clone());
+                progress.inc();
+                continue;
+            }
+            Ok(Sent::Forbidden(message)) => (Some(403), format!("{}: {}", action.permission_hint(), message)),
+            Ok(Sent::RateLimited(status, message)) => (Some(status.as_u16()), format!("rate limited: {}", message)),
+            Ok(Sent::Response(response)) => {
+                let status = response.status();
+                (Some(status.as_u16()), error_message(response).await)
+            }
+            Err(e) => (None, describe_request_error(&e)),
+        };
+
+        record(repo, "failed", status, Some(&error));
+        progress.log(|| error!("Failed to {} {}: {}", action.verb(), repo.name, error));
+        summary.failed.push(FailedDelete { full_name: repo.full_name.clone(), status, error });
+        progress.inc();
+    }
+    progress.finish();
+    drop(catch_ctrl_c);
+
+    let exit = if interrupted() {
+        summary.skipped.extend(remaining.map(|repo| repo.full_name.clone()));
+        format.print_human(&format!(
+            "\nInterrupted, {} repositories were not processed.\n",
+            summary.skipped.len() - skipped_before
+        ));
+        Exit::Interrupted
+    } else {
+        Exit::Success
+    };
+    if text {
+        print_tally(summary, action, args.dry_run, summary.skipped.len() - skipped_before);
+    }
+    exit
+}
+
+/// Styles a success line, which is logged to stderr, in green.
+fn success(line: String) -> console::StyledObject<String> {
+    style(line).for_stderr().green()
+}
+
+/// Set while a [`CatchCtrlC`] guard is alive.
+static CATCHING_CTRL_C: AtomicBool = AtomicBool::new(false);
+
+/// Set by the first Ctrl-C while it is caught.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Wakes [`until_interrupted`] when [`INTERRUPTED`] is set.
+static INTERRUPT: Notify = Notify::const_new();
+
+/// Listens for Ctrl-C for the rest of the process; tokio's handler replaces the default one
+/// for good, so there is only ever this one. While a [`CatchCtrlC`] guard is alive the first
+/// press sets [`INTERRUPTED`], so the current request can finish and the rest are left alone.
+/// A second press, or one at any other time such as at a prompt, quits at once.
+fn listen_for_ctrl_c() {
+    tokio::spawn(async {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            if !CATCHING_CTRL_C.load(Ordering::SeqCst) || INTERRUPTED.swap(true, Ordering::SeqCst) {
+                eprintln!();
+                std::process::exit(Exit::Interrupted as i32);
+            }
+            eprintln!("\nStopping, press Ctrl-C again to quit immediately");
+            INTERRUPT.notify_waiters();
+        }
+    });
+}
+
+/// Turns Ctrl-C into an interruption, see [`interrupted`], instead of quitting, until
+/// dropped.
+struct CatchCtrlC;
+
+impl CatchCtrlC {
+    fn start() -> Self {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        CATCHING_CTRL_C.store(true, Ordering::SeqCst);
+        CatchCtrlC
+    }
+}
+
+impl Drop for CatchCtrlC {
+    fn drop(&mut self) {
+        CATCHING_CTRL_C.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Whether Ctrl-C was pressed since the last [`CatchCtrlC::start`].
+fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Resolves once Ctrl-C is pressed while it is caught, or right away if it already was.
+async fn until_interrupted() {
+    let notified = INTERRUPT.notified();
+    tokio::pin!(notified);
+    // Registered before the check, so a press in between is not missed.
+    notified.as_mut().enable();
+    if !interrupted() {
+        notified.await;
+    }
+}
+
+/// Archives the selected repositories, waits `--grace` seconds and only then deletes them.
+/// Pressing Ctrl-C during the wait aborts, leaving the repositories archived but intact.
+/// Repositories that fail to archive are not deleted.
+async fn safe_delete(
+    github: &GitHubClient,
+    repos: Vec<&Repo>,
+    args: &Args,
+    audit: Option<&AuditLog>,
+    summary: &mut Summary,
+) -> Exit {
+    let format = args.format;
+    let (already_archived, to_archive): (Vec<&Repo>, Vec<&Repo>) =
+        repos.iter().partition(|repo| repo.archived == Some(true));
+
+    // The archive step gets its own summary so its tally and the JSON output stay separate
+    // from the deletions; its failures are merged in at the end.
+    let mut archive_summary = Summary::default();
+    if !to_archive.is_empty()
+        && process_repos(github, to_archive, &Action::Archive, args, audit, &mut archive_summary).await
+            == Exit::Interrupted
+    {
+        summary.failed.append(&mut archive_summary.failed);
+        summary.archived = archive_summary.archived;
+        summary.skipped.append(&mut archive_summary.skipped);
+        return Exit::Interrupted;
+    }
+    summary.failed.append(&mut archive_summary.failed);
+
+    let to_delete: Vec<&Repo> = if args.dry_run {
+        repos
+    } else {
+        repos
+            .into_iter()
+            .filter(|repo| {
+                already_archived.iter().any(|r| r.full_name == repo.full_name)
+                    || archive_summary.archived.contains(&repo.full_name)
+            })
+            .collect()
+    };
+    if to_delete.is_empty() {
+        return Exit::Success;
+    }
+
+    if !args.dry_run {
+        let mut listing = format!(
+            "\nThese repositories are archived and will be deleted in {}s, press Ctrl-C to abort:\n",
+            args.grace
+        );
+        for repo in &to_delete {
+            listing.push_str(&format!("  {}\n", repo.full_name));
+        }
+        format.print_human(&listing);
+
+        let _catch_ctrl_c = CatchCtrlC::start();
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(args.grace)) => {}
+            _ = until_interrupted() => {
+                format.print_human("\nAborted, the repositories stay archived and were not deleted.\n");
+                summary.archived = archive_summary.archived;
+                return Exit::Aborted;
+            }
+        }
+    }
+
+    let exit = process_repos(github, to_delete, &Action::Delete, args, audit, summary).await;
+    summary.archived = archive_summary.archived.into_iter().filter(|name| !summary.deleted.contains(name)).collect();
+    exit
+}
+
+/// Progress bar for the processing loop. It is hidden when stdout is not a terminal, in JSON
+/// mode and for dry runs. Log lines are routed through [`Progress::log`] so they are printed
+/// above the bar instead of tearing it.
+struct Progress(Option<ProgressBar>);
+
+impl Progress {
+    fn new(len: usize, action: &Action, args: &Args) -> Self {
+        if args.dry_run || args.quiet || args.format != OutputFormat::Text || !io::stdout().is_terminal() {
+            return Progress(None);
+        }
+
+        let bar = ProgressBar::with_draw_target(Some(len as u64), ProgressDrawTarget::stdout());
+        let template = format!("[{{bar:40}}] {{pos}}/{{len}} {}", action.past_tense());
+        if let Ok(style) = ProgressStyle::with_template(&template) {
+            bar.set_style(style.progress_chars("=> "));
+        }
+        Progress(Some(bar))
+    }
+
+    fn log(&self, f: impl FnOnce()) {
+        match &self.0 {
+            Some(bar) => bar.suspend(f),
+            None => f(),
+        }
+    }
+
+    fn inc(&self) {
+        if let Some(bar) = &self.0 {
+            bar.inc(1);
+        }
+    }
+
+    fn finish(&self) {
+        if let Some(bar) = &self.0 {
+            bar.finish();
+        }
+    }
+}
+
+fn print_tally(summary: &Summary, action: &Action, dry_run: bool, skipped: usize) {
+    // Every failure was already logged as an error, but an --output file still gets the tally.
+    if quiet() && OUTPUT.get().is_none() {
+        return;
+    }
+    if dry_run {
+        print_summary(&format!("\nDry run: would {} {}, skipped {}\n", action.verb(), summary.dry_run.len(), skipped));
+        return;
+    }
+
+    let mut tally = String::new();
+    if let Some(table) = result_table(summary, action) {
+        tally.push_str(&format!("\n{}", table));
+    }
+    tally.push_str(&format!("\n{}\n", tally_line(summary, action, skipped)));
+    // GitLab reports no sizes, and a 0 would only suggest that nothing was freed.
+    match summary.reclaimed_kb {
+        0 => {}
+        kb @ 1..1024 => tally.push_str(&format!("Reclaimed ~{} KB\n", kb)),
+        kb => tally.push_str(&format!("Reclaimed ~{:.1} MB\n", kb as f64 / 1024.0)),
+    }
+    print_summary(&tally);
+}
+
+/// Lays out what happened to each repository, with failures last so they stay in view:
+///
+/// ```text
+/// Repo       Status  Detail
+/// me/one     ✓       deleted
+/// me/two     —       skipped
+/// me/three   ✗       403 Forbidden (Must have admin rights to Repository.)
+/// ```
+///
+/// The status is colored when colors are on. `None` when there is nothing to show.
+fn result_table(summary: &Summary, action: &Action) -> Option<String> {
+    let done = action.done(summary).iter().map(|name| (name, style("✓").green(), action.past_tense()));
+    let skipped = summary.skipped.iter().map(|name| (name, style("—").dim(), "skipped"));
+    let failed = summary.failed.iter().map(|failure| (&failure.full_name, style("✗").red(), failure.error.as_str()));
+    let rows: Vec<_> = done.chain(skipped).chain(failed).collect();
+    if rows.is_empty() {
+        return None;
+    }
+
+    let width = rows.iter().map(|(name, _, _)| name.chars().count()).max().unwrap_or(0).max("Repo".len());
+    let mut table = format!("{:<width$}  Status  Detail\n", "Repo", width = width);
+    for (name, status, detail) in rows {
+        // The status is a single character, padded by hand since the colors would throw
+        // off `{:<}`.
+        table.push_str(&format!("{:<width$}  {}       {}\n", name, status, detail, width = width));
+    }
+    Some(table)
+}
 
-        let url = format!("https://api.github.com/repos/{}", repo.full_name);
-        let response = client.delete(&url).headers(headers).send().await?;
+/// Counts what happened, e.g. `Deleted 3, failed 1, skipped 0`. A dry run is counted as such.
+fn tally_line(summary: &Summary, action: &Action, skipped: usize) -> String {
+    if !summary.dry_run.is_empty() {
+        return format!("Dry run: would {} {}, skipped {}", action.verb(), summary.dry_run.len(), skipped);
+    }
+    let succeeded = match action {
+        Action::Delete => format!("Deleted {}", summary.deleted.len()),
+        Action::Archive => format!("Archived {}", summary.archived.len()),
+        Action::Transfer(_) => format!("Transferred {}", summary.transferred.len()),
+        Action::Rename(_) => format!("Renamed {}", summary.renamed.len()),
+        Action::Clear => format!("Cleared {}", summary.cleared.len()),
+        Action::DisablePages => format!("Unpublished {}", summary.unpublished.len()),
+    };
+    format!("{}, failed {}, skipped {}", succeeded, summary.failed.len(), skipped)
+}
 
-        if response.status().is_success() {
-            println!("Successfully deleted {}", repo.name);
+/// Renders a request as a curl command line with the token in the Authorization header
+/// replaced by `***`.
+fn curl_command(request: &reqwest::Request) -> String {
+    let mut command = format!("curl -X {}", request.method());
+    for (name, value) in request.headers() {
+        let value = if name == AUTHORIZATION {
+            let scheme = value.to_str().ok().and_then(|v| v.split(' ').next()).unwrap_or("token");
+            format!("{} ***", scheme)
+        } else if value.is_sensitive() {
+            // GitLab's PRIVATE-TOKEN header carries the bare token.
+            "***".to_string()
         } else {
-            println!("Failed to delete {}: {}", repo.name, response.status());
+            value.to_str().unwrap_or_default().to_string()
+        };
+        command.push_str(&format!(" -H {}", shell_quote(&format!("{}: {}", name, value))));
+    }
+    if let Some(body) = request.body().and_then(|body| body.as_bytes()) {
+        command.push_str(&format!(" -d {}", shell_quote(&String::from_utf8_lossy(body))));
+    }
+    command.push_str(&format!(" {}", shell_quote(request.url().as_str())));
+    command
+}
+
+/// Quotes `value` for a POSIX shell, e.g. a description with a `'` in a request body.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Spacing between requests while processing repositories. It grows each time GitHub
+/// reports a rate limit and shrinks again while requests go through, so a burst of
+/// operations slows itself down instead of tripping the secondary rate limits repeatedly.
+/// On top of that, `--delay-ms` keeps a fixed minimum between two request starts.
+struct Throttle {
+    delay: Duration,
+    gap: Duration,
+    last_start: Option<Instant>,
+}
+
+impl Throttle {
+    const MIN: Duration = Duration::from_millis(500);
+    const MAX: Duration = Duration::from_secs(30);
+
+    fn new(gap: Duration) -> Self {
+        Throttle { delay: Duration::ZERO, gap, last_start: None }
+    }
+
+    async fn wait(&mut self) {
+        if !self.delay.is_zero() {
+            tokio::time::sleep(self.delay).await;
+        }
+        if let Some(last_start) = self.last_start {
+            let since = last_start.elapsed();
+            if since < self.gap {
+                tokio::time::sleep(self.gap - since).await;
+            }
         }
+        self.last_start = Some(Instant::now());
+    }
+
+    fn rate_limited(&mut self) {
+        self.delay = (self.delay * 2).clamp(Self::MIN, Self::MAX);
+    }
+
+    fn succeeded(&mut self) {
+        self.delay /= 2;
+        if self.delay < Self::MIN {
+            self.delay = Duration::ZERO;
+        }
+    }
+}
+
+/// How a request sent by [`send_with_retry`] ended.
+enum Sent {
+    Response(reqwest::Response),
+    /// Still rate limited when the retries ran out, or the limit resets after `--max-wait`.
+    RateLimited(StatusCode, String),
+    /// A `403` that is a genuine permission denial, with GitHub's message. The body has to
+    /// be read to tell it from a secondary rate limit.
+    Forbidden(String),
+}
+
+/// How long to wait for a secondary rate limit that came without a `Retry-After`; GitHub
+/// asks for at least a minute.
+const SECONDARY_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// Sends the request built by `request`, retrying with exponential backoff (250ms, 500ms,
+/// 1s, ...) on 5xx responses and network errors. A 403 or 429 that is a rate limit, told by
+/// its headers or by GitHub's secondary rate limit message, pauses until the limit resets
+/// (at most `--max-wait`) and is retried as well. Other 4xx responses are returned
+/// immediately.
+async fn send_with_retry(
+    request: impl Fn() -> reqwest::RequestBuilder,
+    name: &str,
+    args: &Args,
+    progress: &Progress,
+    throttle: &mut Throttle,
+) -> Result<Sent, reqwest::Error> {
+    let max_retries = args.max_retries;
+    let mut attempt = 0;
+    loop {
+        throttle.wait().await;
+        let started = Instant::now();
+        let result = request().send().await;
+        progress.log(|| match &result {
+            Ok(response) => debug!("Request for {} returned {} in {:?}", name, response.status(), started.elapsed()),
+            Err(e) => debug!("Request for {} failed after {:?}: {}", name, started.elapsed(), e),
+        });
+        let mut rate_limit = None;
+        // `last` is what to report if this attempt is the last one.
+        let (reason, last) = match result {
+            Ok(response) if response.status().is_server_error() => {
+                (response.status().to_string(), Ok(Sent::Response(response)))
+            }
+            // Retrying cannot help until the token is authorized; the error says where.
+            Ok(response) if response.status() == StatusCode::FORBIDDEN && sso_url(response.headers()).is_some() => {
+                return Ok(Sent::Response(response));
+            }
+            Ok(response)
+                if matches!(response.status(), StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS) =>
+            {
+                let status = response.status();
+                let wait = rate_limit_wait(response.headers());
+                let message = error_message(response).await;
+                let wait = match wait {
+                    Some(wait) => wait,
+                    None if status == StatusCode::FORBIDDEN && !is_secondary_rate_limit(&message) => {
+                        return Ok(Sent::Forbidden(message));
+                    }
+                    None => SECONDARY_RATE_LIMIT_WAIT,
+                };
+                rate_limit = Some(wait);
+                ("rate limit".to_string(), Ok(Sent::RateLimited(status, message)))
+            }
+            Ok(response) => {
+                throttle.succeeded();
+                return Ok(Sent::Response(response));
+            }
+            Err(e) => (describe_request_error(&e), Err(e)),
+        };
+        if attempt >= max_retries {
+            return last;
+        }
+
+        let mut delay = Duration::from_millis(250 * 2u64.pow(attempt));
+        if let Some(wait) = rate_limit {
+            throttle.rate_limited();
+            if wait.as_secs() > args.max_wait {
+                progress.log(|| {
+                    warn!("Rate limit for {} resets in {}s, longer than --max-wait", name, wait.as_secs())
+                });
+                return last;
+            }
+            delay = wait;
+            progress.log(|| debug!("Spacing requests {}ms apart after a rate limit", throttle.delay.as_millis()));
+        }
+        attempt += 1;
+        progress.log(|| {
+            warn!(
+                "Retrying {} in {}ms after {} (attempt {}/{})",
+                name,
+                delay.as_millis(),
+                reason,
+                attempt,
+                max_retries
+            )
+        });
+        // Ctrl-C should not have to wait out a backoff or a rate limit of up to --max-wait.
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = until_interrupted() => return last,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Selects from `len` repositories named `repo-1` to `repo-<len>`.
+    fn select(input: &str, len: usize) -> Result<Vec<usize>, String> {
+        let names: Vec<String> = (1..=len).map(|i| format!("repo-{}", i)).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        parse_selection(input, &names)
+    }
+
+    fn repo(name: &str) -> Repo {
+        serde_json::from_value(serde_json::json!({ "name": name, "full_name": format!("me/{}", name) })).unwrap()
+    }
+
+    /// Runs `repos` through `process_repos` against `server`, as a normal run with the extra
+    /// `flags` would; without any, they are deleted.
+    async fn process_with_mock(server: &wiremock::MockServer, flags: &[&str], repos: &[Repo]) -> Summary {
+        let uri = server.uri();
+        let args = Args::parse_from(["repo-deleter", "--base-url", &uri, "--max-retries", "0"].iter().chain(flags));
+        let github = GitHubClient::new("ghp_test".to_string(), uri, Duration::from_secs(5), Provider::GitHub).unwrap();
+        let mut summary = Summary::default();
+        process_repos(&github, repos.iter().collect(), &Action::from_args(&args), &args, None, &mut summary).await;
+        summary
+    }
+
+    #[tokio::test]
+    async fn delete_sends_authenticated_deletes_to_each_repository() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for name in ["one", "two"] {
+            Mock::given(method("DELETE"))
+                .and(path(format!("/repos/me/{}", name)))
+                .and(header("authorization", "token ghp_test"))
+                .respond_with(ResponseTemplate::new(204))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let mut one = repo("one");
+        one.size = Some(2048);
+        let summary = process_with_mock(&server, &[], &[one, repo("two")]).await;
+        assert_eq!(summary.deleted, ["me/one", "me/two"]);
+        assert!(summary.failed.is_empty());
+        // "two" has no size and counts as 0.
+        assert_eq!(summary.reclaimed_kb, 2048);
+    }
+
+    #[tokio::test]
+    async fn delete_reports_403_as_failure() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/repos/me/locked"))
+            .respond_with(
+                ResponseTemplate::new(403).set_body_json(serde_json::json!({ "message": "Must have admin rights to Repository." })),
+            )
+            .mount(&server)
+            .await;
+
+        let summary = process_with_mock(&server, &[], &[repo("locked")]).await;
+        assert!(summary.deleted.is_empty());
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].full_name, "me/locked");
+        assert_eq!(summary.failed[0].status, Some(403));
+        assert!(summary.failed[0].error.contains("delete_repo"), "unexpected error: {}", summary.failed[0].error);
+    }
+
+    #[tokio::test]
+    async fn delete_reports_a_secondary_rate_limit_without_the_permission_hint() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let message = "You have exceeded a secondary rate limit. Please wait a few minutes before you try again.";
+        Mock::given(method("DELETE"))
+            .and(path("/repos/me/busy"))
+            .respond_with(ResponseTemplate::new(403).set_body_json(serde_json::json!({ "message": message })))
+            .mount(&server)
+            .await;
+
+        let summary = process_with_mock(&server, &[], &[repo("busy")]).await;
+        assert_eq!(summary.failed.len(), 1);
+        let error = &summary.failed[0].error;
+        assert!(error.starts_with("rate limited"), "unexpected error: {}", error);
+        assert!(!error.contains("delete_repo"), "unexpected error: {}", error);
+    }
+
+    #[tokio::test]
+    async fn disable_pages_skips_repositories_without_a_site() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for (name, status) in [("site", 204), ("plain", 404)] {
+            Mock::given(method("DELETE"))
+                .and(path(format!("/repos/me/{}/pages", name)))
+                .respond_with(ResponseTemplate::new(status))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let summary = process_with_mock(&server, &["--disable-pages"], &[repo("site"), repo("plain")]).await;
+        assert_eq!(summary.unpublished, ["me/site"]);
+        assert_eq!(summary.skipped, ["me/plain"]);
+        assert!(summary.deleted.is_empty() && summary.failed.is_empty());
+    }
+
+    #[test]
+    fn curl_command_quotes_the_body() {
+        let base_url = DEFAULT_BASE_URL.to_string();
+        let github = GitHubClient::new("ghp_test".to_string(), base_url, Duration::from_secs(5), Provider::GitHub).unwrap();
+        let request = github.rename_repo("me/old", "tmp-it's").build().unwrap();
+        let command = curl_command(&request);
+        assert!(command.contains(r#"-d '{"name":"tmp-it'\''s"}'"#), "unexpected command: {}", command);
+        assert!(command.contains("-H 'authorization: token ***'"), "unexpected command: {}", command);
+        assert!(command.ends_with(" 'https://api.github.com/repos/me/old'"), "unexpected command: {}", command);
+    }
+
+    #[test]
+    fn preview_groups_the_selection_by_visibility() {
+        let mut private = repo("secret");
+        private.private = Some(true);
+        let mut fork = repo("fork");
+        fork.private = Some(false);
+        fork.fork = Some(true);
+        let repos = [private, fork, repo("gitlab")];
+        let selected: Vec<&Repo> = repos.iter().collect();
+        let line = console::strip_ansi_codes(&preview(&Action::Delete, &selected, OutputFormat::Text, false)).to_string();
+        assert_eq!(line, "About to delete 1 public, 1 private, 1 of unknown visibility, 1 fork");
+        let line = preview(&Action::Archive, &selected[1..2], OutputFormat::Text, true);
+        assert_eq!(line, "Would archive 1 public, 0 private, 1 fork");
+    }
+
+    #[test]
+    fn state_file_keeps_the_instant_across_timezones() {
+        let path = std::env::temp_dir().join(format!("repo-deleter-state-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert_eq!(read_state(&path).unwrap(), None);
+
+        fs::write(&path, r#"{"last_run":"2024-03-01T02:30:00+02:00"}"#).unwrap();
+        let last_run = read_state(&path).unwrap().unwrap();
+        assert_eq!(last_run.to_rfc3339(), "2024-03-01T00:30:00+00:00");
+
+        let args = Args::parse_from(["repo-deleter", "--state-file", path.to_str().unwrap(), "--dry-run"]);
+        save_state(&args, Utc::now(), Exit::Success);
+        assert_eq!(read_state(&path).unwrap(), Some(last_run));
+        let args = Args::parse_from(["repo-deleter", "--state-file", path.to_str().unwrap()]);
+        save_state(&args, last_run + chrono::Duration::days(7), Exit::Success);
+        assert_eq!(read_state(&path).unwrap(), Some(last_run + chrono::Duration::days(7)));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn cache_is_not_reused_for_another_account() {
+        let path = std::env::temp_dir().join(format!("repo-deleter-cache-{}.json", std::process::id()));
+        let args = Args::parse_from(["repo-deleter", "--cache", path.to_str().unwrap()]);
+        save_cache(&path, &args, "alice", vec![repo("old")]);
+        assert_eq!(load_cache(&args, "alice").map(|repos| repos.len()), Some(1));
+        assert!(load_cache(&args, "bob").is_none());
+        let args = Args::parse_from(["repo-deleter", "--cache", path.to_str().unwrap(), "--provider", "gitlab"]);
+        assert!(load_cache(&args, "alice").is_none());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn all_needs_the_acknowledgement() {
+        assert!(Args::try_parse_from(["repo-deleter", "--org", "acme", "--all"]).is_err());
+        let args =
+            Args::try_parse_from(["repo-deleter", "--org", "acme", "--all", "--i-understand-this-is-permanent"]).unwrap();
+        assert!(args.all);
+    }
+
+    #[test]
+    fn clear_default_branch_needs_strict_confirm_and_yes() {
+        for flags in [&["--strict-confirm"][..], &["--yes"], &[]] {
+            let args = ["repo-deleter", "--clear-default-branch"].iter().chain(flags);
+            assert!(Args::try_parse_from(args).is_err(), "accepted with {:?}", flags);
+        }
+        let args = Args::try_parse_from(["repo-deleter", "--clear-default-branch", "--strict-confirm", "--yes"]).unwrap();
+        assert!(args.clear_default_branch);
+    }
+
+    #[test]
+    fn token_file_is_trimmed_and_must_not_be_empty() {
+        let path = std::env::temp_dir().join(format!("repo-deleter-token-{}", std::process::id()));
+        fs::write(&path, "  ghp_secret\n").unwrap();
+        assert_eq!(read_token_file(&path).unwrap(), "ghp_secret");
+        fs::write(&path, "\n").unwrap();
+        assert!(read_token_file(&path).unwrap_err().contains("is empty"));
+        fs::remove_file(&path).unwrap();
+        assert!(read_token_file(&path).unwrap_err().starts_with("Failed to read token file"));
+    }
+
+    #[test]
+    fn from_file_cannot_select_unlisted_repositories() {
+        let names = parse_name_list("me/one\nvictim/one\nme/../victim/two\nME/ONE\n");
+        let valid: Vec<&String> = names.iter().filter(|name| is_full_name(name, Provider::GitHub)).collect();
+        assert_eq!(valid, ["me/one", "victim/one", "ME/ONE"]);
+        assert!(!is_full_name("me/one?x=1", Provider::GitHub));
+        assert!(is_full_name("group/sub/project", Provider::GitLab));
+
+        let repos = [repo("one"), repo("two")];
+        let selected = select_by_name(&repos, &names, OutputFormat::Text);
+        let selected: Vec<&str> = selected.iter().map(|repo| repo.full_name.as_str()).collect();
+        assert_eq!(selected, ["me/one"]);
+    }
+
+    #[test]
+    fn result_table_lists_failures_last() {
+        let summary = Summary {
+            deleted: vec!["me/one".to_string()],
+            skipped: vec!["me/two".to_string()],
+            failed: vec![FailedDelete { full_name: "me/three".to_string(), status: Some(403), error: "denied".to_string() }],
+            ..Summary::default()
+        };
+        let table = result_table(&summary, &Action::Delete).unwrap();
+        assert_eq!(
+            console::strip_ansi_codes(&table),
+            "Repo      Status  Detail\n\
+             me/one    ✓       deleted\n\
+             me/two    —       skipped\n\
+             me/three  ✗       denied\n"
+        );
+        assert!(result_table(&Summary::default(), &Action::Delete).is_none());
+    }
+
+    #[tokio::test]
+    async fn throttle_keeps_the_delay_between_request_starts() {
+        let mut throttle = Throttle::new(Duration::from_millis(40));
+        let started = Instant::now();
+        throttle.wait().await;
+        assert!(started.elapsed() < Duration::from_millis(40), "the first request waited");
+        throttle.wait().await;
+        throttle.wait().await;
+        assert!(started.elapsed() >= Duration::from_millis(80));
+    }
+
+    #[test]
+    fn guards_flag_profile_repositories_and_protected_patterns() {
+        let args = Args::parse_from(["repo-deleter", "--protect-pattern", "prod-*"]);
+        let guards = Guards::from_args(&args).unwrap();
+        assert!(guards.reason(&repo("me")).is_some());
+        assert!(guards.reason(&repo(".github")).is_some());
+        assert_eq!(guards.reason(&repo("Prod-api")).as_deref(), Some("matches --protect-pattern prod-*"));
+        assert_eq!(guards.reason(&repo("scratch")), None);
+    }
+
+    #[test]
+    fn flags_are_accepted_after_the_subcommand() {
+        let matches = command().try_get_matches_from(["repo-deleter", "list", "--org", "acme"]).unwrap();
+        let args = Args::from_arg_matches(&matches).unwrap();
+        assert!(matches!(args.command, Some(Command::List)));
+        assert_eq!(args.org.as_deref(), Some("acme"));
+    }
+
+    #[test]
+    fn expand_env_replaces_variables() {
+        std::env::set_var("REPO_DELETER_TEST_HOST", "ghe.example.com");
+        assert_eq!(expand_env("https://${REPO_DELETER_TEST_HOST}/api/v3").unwrap(), "https://ghe.example.com/api/v3");
+        assert_eq!(expand_env("cost$5").unwrap(), "cost$5");
+        let missing = expand_env("${REPO_DELETER_TEST_UNSET}").unwrap_err();
+        assert!(missing.contains("REPO_DELETER_TEST_UNSET"), "{}", missing);
+        assert!(expand_env("${REPO_DELETER_TEST_HOST").is_err());
+    }
+
+    #[test]
+    fn parse_selection_accepts_numbers_and_ranges() {
+        assert_eq!(select("1,3-5,7", 10), Ok(vec![0, 2, 3, 4, 6]));
+    }
+
+    #[test]
+    fn parse_selection_keeps_input_order_and_drops_duplicates() {
+        assert_eq!(select("4,2,4,1-3", 5), Ok(vec![3, 1, 0, 2]));
+    }
+
+    #[test]
+    fn parse_selection_ignores_whitespace() {
+        assert_eq!(select(" 1 , 3 - 4 ,, 6 ", 6), Ok(vec![0, 2, 3, 5]));
+    }
+
+    #[test]
+    fn parse_selection_ignores_zero() {
+        assert_eq!(select("0", 3), Ok(vec![]));
+        assert_eq!(select("0-2", 3), Ok(vec![0, 1]));
+    }
+
+    #[test]
+    fn parse_selection_ignores_numbers_beyond_len() {
+        assert_eq!(select("2,4,9", 3), Ok(vec![1]));
+        assert_eq!(select("2-9", 3), Ok(vec![1, 2]));
+        assert_eq!(select("1", 0), Ok(vec![]));
+    }
+
+    #[test]
+    fn parse_selection_reversed_range_selects_nothing() {
+        assert_eq!(select("5-3", 10), Ok(vec![]));
+        assert_eq!(select("5-3,1", 10), Ok(vec![0]));
+    }
+
+    #[test]
+    fn parse_selection_reports_malformed_ranges() {
+        assert_eq!(select("-2,3-,2", 5), Err("Not a number, range or valid glob: -2, 3-".to_string()));
+        assert_eq!(select("1-2-3", 5), Err("Not a number, range or valid glob: 1-2-3".to_string()));
+        assert_eq!(select("", 5), Ok(vec![]));
+    }
+
+    #[test]
+    fn parse_selection_accepts_leading_zeros() {
+        assert_eq!(select("01,002-003", 5), Ok(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn parse_selection_expands_globs() {
+        let names = ["old-api", "web", "old-site", "api-backup", "Old-cli"];
+        assert_eq!(parse_selection("old-*", &names), Ok(vec![0, 2, 4]));
+        assert_eq!(parse_selection("*-backup", &names), Ok(vec![3]));
+        assert_eq!(parse_selection("web", &names), Ok(vec![1]));
+        assert_eq!(parse_selection("nothing-*", &names), Ok(vec![]));
+    }
+
+    #[test]
+    fn parse_selection_mixes_globs_with_numbers() {
+        let names = ["a", "b", "c", "d", "old-1", "old-2"];
+        assert_eq!(parse_selection("1,3-4,old-*,2", &names), Ok(vec![0, 2, 3, 4, 5, 1]));
+        assert_eq!(parse_selection("5,old-*", &names), Ok(vec![4, 5]));
+    }
+
+    #[test]
+    fn parse_selection_reports_invalid_globs() {
+        let names = ["a"];
+        assert_eq!(parse_selection("a,[z", &names), Err("Not a number, range or valid glob: [z".to_string()));
     }
-    Ok(())
 }
@@ -0,0 +1,178 @@
+use std::fs;
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use globset::{GlobBuilder, GlobMatcher};
+use serde::Deserialize;
+use repo_deleter::{Filters, TopicMatch, Visibility};
+use crate::{parse_age, parse_date};
+
+/// A `--rules` file describing which repositories a recurring cleanup deletes. Every rule
+/// narrows the selection further, on top of any filter flags.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Rules {
+    /// Name globs; when any are given, a repository must match one of them.
+    #[serde(default)]
+    include: Vec<String>,
+    /// Name globs a repository must not match.
+    #[serde(default)]
+    exclude: Vec<String>,
+    older_than: Option<String>,
+    created_before: Option<String>,
+    created_after: Option<String>,
+    visibility: Option<Visibility>,
+    #[serde(default)]
+    empty_only: bool,
+    #[serde(default)]
+    forks_only: bool,
+    #[serde(default)]
+    archived_only: bool,
+    #[serde(default)]
+    no_description: bool,
+    #[serde(default)]
+    topics: Vec<String>,
+    topic_match: Option<TopicMatch>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+}
+
+impl Rules {
+    /// Reads the rules in `path`. A file that narrows nothing, e.g. an empty one or one with
+    /// only `exclude`, would select every repository without the confirmation `--all` asks
+    /// for, so it is rejected.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read rules {}: {}", path.display(), e))?;
+        let rules: Rules =
+            serde_yaml::from_str(&contents).map_err(|e| format!("Invalid rules {}: {}", path.display(), e))?;
+        if !rules.narrows() {
+            return Err(format!(
+                "The rules in {} would select every repository; add at least one of include, older_than, \
+                 created_before, created_after, min_size, max_size, topics or one of the *_only rules",
+                path.display()
+            ));
+        }
+        Ok(rules)
+    }
+
+    /// Whether any rule leaves repositories out other than `exclude` and `visibility`, which
+    /// still keep all but a few, or all of one kind.
+    fn narrows(&self) -> bool {
+        !self.include.is_empty()
+            || self.older_than.is_some()
+            || self.created_before.is_some()
+            || self.created_after.is_some()
+            || self.min_size.is_some()
+            || self.max_size.is_some()
+            || !self.topics.is_empty()
+            || self.empty_only
+            || self.forks_only
+            || self.archived_only
+            || self.no_description
+    }
+
+    /// Adds the rules to `filters`. Where a rule and a flag restrict the same field, the
+    /// stricter of the two wins.
+    pub fn narrow(self, filters: &mut Filters) -> Result<(), String> {
+        filters.include.extend(globs(&self.include, "include")?);
+        filters.exclude.extend(globs(&self.exclude, "exclude")?);
+
+        if let Some(age) = &self.older_than {
+            let age = parse_age(age).map_err(|e| format!("Invalid older_than rule: {}", e))?;
+            filters.pushed_before = earliest(filters.pushed_before, Utc::now() - age);
+        }
+        if let Some(date) = &self.created_before {
+            let date = parse_date(date).map_err(|e| format!("Invalid created_before rule: {}", e))?;
+            filters.created_before = earliest(filters.created_before, date);
+        }
+        if let Some(date) = &self.created_after {
+            let date = parse_date(date).map_err(|e| format!("Invalid created_after rule: {}", e))?;
+            filters.created_after = Some(filters.created_after.map_or(date, |after| after.max(date)));
+        }
+
+        match self.visibility {
+            None | Some(Visibility::All) => {}
+            Some(visibility) if filters.visibility == Visibility::All => filters.visibility = visibility,
+            Some(visibility) if visibility != filters.visibility => {
+                return Err("The visibility rule contradicts --visibility, so nothing could match".to_string())
+            }
+            Some(_) => {}
+        }
+
+        filters.empty_only |= self.empty_only;
+        filters.forks_only |= self.forks_only;
+        filters.archived_only |= self.archived_only;
+        filters.no_description |= self.no_description;
+
+        if !self.topics.is_empty() {
+            if !filters.topics.is_empty() {
+                return Err("Topics cannot be given both in the rules and with --topic".to_string());
+            }
+            filters.topics = self.topics;
+            filters.topic_match = self.topic_match.unwrap_or_default();
+        }
+
+        if let Some(min) = self.min_size {
+            filters.min_size = Some(filters.min_size.map_or(min, |flag| flag.max(min)));
+        }
+        if let Some(max) = self.max_size {
+            filters.max_size = Some(filters.max_size.map_or(max, |flag| flag.min(max)));
+        }
+        if let (Some(min), Some(max)) = (filters.min_size, filters.max_size) {
+            if min > max {
+                return Err(format!("The minimum size ({} KB) is larger than the maximum size ({} KB)", min, max));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn earliest(current: Option<DateTime<Utc>>, rule: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    Some(current.map_or(rule, |current| current.min(rule)))
+}
+
+/// Compiles the `include` or `exclude` globs, which match names case-insensitively like the
+/// selection prompt does.
+fn globs(patterns: &[String], key: &str) -> Result<Vec<GlobMatcher>, String> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            GlobBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map(|glob| glob.compile_matcher())
+                .map_err(|e| format!("Invalid {} rule {}: {}", key, pattern, e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_narrow_the_flags() {
+        let rules: Rules = serde_yaml::from_str("include: [old-*]\nvisibility: private\nmin_size: 10\n").unwrap();
+        let mut filters = Filters { min_size: Some(50), ..Filters::default() };
+        rules.narrow(&mut filters).unwrap();
+        assert_eq!(filters.include.len(), 1);
+        assert_eq!(filters.visibility, Visibility::Private);
+        assert_eq!(filters.min_size, Some(50));
+
+        let rules: Rules = serde_yaml::from_str("visibility: public\n").unwrap();
+        assert!(rules.narrow(&mut filters).is_err());
+    }
+
+    #[test]
+    fn rules_that_narrow_nothing_are_rejected() {
+        let path = std::env::temp_dir().join(format!("repo-deleter-rules-{}.yml", std::process::id()));
+        for contents in ["", "exclude: [keep-*]\n", "visibility: all\n"] {
+            fs::write(&path, contents).unwrap();
+            let e = Rules::read(&path).unwrap_err();
+            assert!(e.contains("would select every repository"), "unexpected error: {}", e);
+        }
+        fs::write(&path, "exclude: [keep-*]\nforks_only: true\n").unwrap();
+        assert!(Rules::read(&path).is_ok());
+        let _ = fs::remove_file(&path);
+    }
+}